@@ -0,0 +1,76 @@
+// src/bin/speech-lint.rs
+// Loads every level the game would and reports anything wrong with them.
+//
+//     cargo run --bin speech-lint [level directory]
+//
+// Exits with status 1 if any level has problems, so it can run in CI.
+use speech_game::game::level::{load_level_path, LevelFormat};
+use speech_game::levels::error::LevelError;
+use speech_game::levels::validate::{self, Problem};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const DEFAULT_LEVEL_DIR: &str = "assets/levels";
+
+// Everything wrong with one level file. Err if it couldn't be loaded at all.
+fn lint_file(path: &Path) -> Result<Vec<Problem>, LevelError> {
+    // ASCII maps are checked as text first, since from_string stops at the first problem
+    if LevelFormat::of(path) == Some(LevelFormat::Ascii) {
+        let text = fs::read_to_string(path)
+            .map_err(|error| LevelError::Io { path: path.to_path_buf(), error })?;
+        let problems = validate::check_ascii(&text);
+        if !problems.is_empty() {
+            return Ok(problems);
+        }
+    }
+
+    let (_, level) = load_level_path(path)?;
+    Ok(validate::check_level(&level))
+}
+
+fn main() -> ExitCode {
+    let dir = std::env::args().nth(1).unwrap_or_else(|| DEFAULT_LEVEL_DIR.to_string());
+
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", dir, e);
+            return ExitCode::FAILURE;
+        },
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| LevelFormat::of(path).is_some())
+        .collect();
+    paths.sort();
+
+    let mut broken = 0;
+    for path in &paths {
+        match lint_file(path) {
+            Ok(problems) if problems.is_empty() => {},
+            Ok(problems) => {
+                broken += 1;
+                for problem in problems {
+                    println!("{}: error: {}", path.display(), problem);
+                }
+            },
+            Err(LevelError::InFile { error, .. }) => {
+                broken += 1;
+                println!("{}: error: {}", path.display(), error);
+            },
+            // These already say which file they're about
+            Err(e) => {
+                broken += 1;
+                println!("error: {}", e);
+            },
+        }
+    }
+
+    println!("Checked {} levels, {} with problems", paths.len(), broken);
+    if broken > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
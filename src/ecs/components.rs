@@ -1,31 +1,136 @@
 // src/ecs/components.rs
 use nalgebra as na;
+use crate::game::entities::player::PlayerAnimation;
+use crate::game::level::Perspective;
 
-// Position component
+// Position component. The centre of the entity, in world pixels.
+#[derive(Clone, Copy, Debug)]
 pub struct Position(pub na::Vector2<f32>);
 
+// Position before the last fixed step, for drawing in between steps
+#[derive(Clone, Copy, Debug)]
+pub struct PreviousPosition(pub na::Vector2<f32>);
+
 // Velocity component
+#[derive(Clone, Copy, Debug)]
 pub struct Velocity(pub na::Vector2<f32>);
 
 // Sprite component
+#[derive(Clone, Debug)]
 pub struct Sprite {
-    pub texture_id: usize,
+    // Name in the sprite atlas. None draws a box of the tint colour, for
+    // things that don't have any art yet.
+    pub name: Option<&'static str>,
     pub width: f32,
     pub height: f32,
+    pub tint: [f32; 4],
+    pub flip_x: bool,
+    // Higher layers are drawn on top
+    pub layer: u8,
 }
 
+// Sprite layers, bottom to top
+pub const NPC_LAYER: u8 = 0;
+pub const EVIDENCE_LAYER: u8 = 1;
+pub const PLAYER_LAYER: u8 = 2;
+
 // Player component (marker for player entity)
 pub struct Player;
 
-// Collider component
+// Collider component. The box is centred on the entity's position.
+#[derive(Clone, Debug)]
 pub struct Collider {
     pub width: f32,
     pub height: f32,
     pub collision_type: CollisionType,
 }
 
+impl Collider {
+    // (left, top, right, bottom) in world pixels when centred on `position`
+    pub fn bounds(&self, position: &Position) -> (f32, f32, f32, f32) {
+        (
+            position.0.x - self.width / 2.0,
+            position.0.y - self.height / 2.0,
+            position.0.x + self.width / 2.0,
+            position.0.y + self.height / 2.0,
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum CollisionType {
     Solid,
     Trigger,
     Evidence,
+}
+
+// Which way the entity is being told to move this step, filled in from the input
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Controller {
+    pub left: bool,
+    pub right: bool,
+    pub up: bool,
+    pub down: bool,
+    // Jump on this step, if standing on something
+    pub jump: bool,
+    pub jump_held: bool,
+}
+
+// How a moving entity is getting about
+#[derive(Clone, Copy, Debug)]
+pub struct MovementState {
+    // Depends on which perspective zone the entity is in
+    pub perspective: Perspective,
+    pub is_grounded: bool,
+    pub is_jumping: bool,
+    pub facing_right: bool,
+    // Holding on to a ladder
+    pub climbing: bool,
+    // Seconds left falling through one-way platforms after Down+Jump
+    pub drop_through: f32,
+    // Seconds left in which a jump still works after walking off a ledge
+    pub coyote_time: f32,
+    // Seconds left in which a jump pressed just before landing still happens
+    pub jump_buffer: f32,
+}
+
+// Which clip an animated sprite is playing, and how far through it
+#[derive(Clone, Copy, Debug)]
+pub struct Animation {
+    pub current: PlayerAnimation,
+    pub frame: usize,
+    pub timer: f32,
+}
+
+// Evidence collected, by id, and the points it was worth
+#[derive(Clone, Debug, Default)]
+pub struct Inventory {
+    pub evidence: Vec<String>,
+    pub score: u32,
+}
+
+// A piece of evidence lying in the level
+#[derive(Clone, Debug)]
+pub struct EvidenceItem {
+    pub id: String,
+    pub points: u32,
+    pub collected: bool,
+}
+
+// Someone who says something when the player walks up to them
+#[derive(Clone, Debug)]
+pub struct Talker {
+    pub name: String,
+    pub dialogue: Option<String>,
+}
+
+// Where the player comes back to after touching a hazard. The spawn point
+// until they reach a checkpoint.
+#[derive(Clone, Copy, Debug)]
+pub struct Respawn(pub na::Vector2<f32>);
+
+// Seconds left flashing after being hurt
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DamageFlash {
+    pub time_left: f32,
 }
\ No newline at end of file
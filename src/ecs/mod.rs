@@ -0,0 +1,6 @@
+// src/ecs/mod.rs
+// Entities, components and the systems that run on them. PlayingState owns
+// the legion World and runs systems::build_schedule() once per fixed step.
+pub mod components;
+pub mod resources;
+pub mod systems;
@@ -0,0 +1,116 @@
+// src/ecs/resources.rs
+// Shared data the systems read and write, besides the components. The
+// levels themselves (game::level::World) are a resource too while the
+// schedule runs, and so is the loader's MovementConfig.
+
+// Length of the step being simulated, in seconds
+pub struct Time {
+    pub delta: f32,
+}
+
+// Which movement keys are held, set from window events
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Input {
+    pub left: bool,
+    pub right: bool,
+    pub up: bool,
+    pub down: bool,
+    // Pressed since the last step. Cleared once the input system has seen it.
+    pub jump: bool,
+    // Still held down since it was pressed. Letting go early cuts the jump short.
+    pub jump_held: bool,
+    // Space is held. Jump is on Up too, so jump_held is either of them.
+    pub space: bool,
+}
+
+impl Input {
+    // Let go of everything, e.g. when a menu takes over the keyboard
+    // and the key-up events would otherwise never arrive
+    pub fn release_all(&mut self) {
+        *self = Input::default();
+    }
+    
+    // Up moves up in top-down areas and jumps in side-scrolling ones. The
+    // systems ignore whichever doesn't apply where the player is.
+    pub fn set_up(&mut self, pressed: bool) {
+        self.up = pressed;
+        self.set_jump_key(pressed, self.space);
+    }
+    
+    // Jump is also bound to Space
+    pub fn set_space(&mut self, pressed: bool) {
+        self.space = pressed;
+        self.set_jump_key(pressed, self.up);
+    }
+    
+    // One of the jump keys went down or up, with `other_held` saying whether
+    // the other one is still down
+    fn set_jump_key(&mut self, pressed: bool, other_held: bool) {
+        if pressed {
+            // Key repeat keeps sending presses while it's held, which aren't new jumps
+            if !self.jump_held {
+                self.jump = true;
+            }
+            self.jump_held = true;
+        } else {
+            // Still jumping if the other key is down
+            self.jump_held = other_held;
+        }
+    }
+}
+
+// Things that happened during a step that the game state wants to know about
+#[derive(Clone, Debug)]
+pub enum GameEvent {
+    EvidenceCollected { id: String, points: u32 },
+    // The player touched a checkpoint they hadn't reached yet
+    CheckpointReached,
+}
+
+#[derive(Default)]
+pub struct Events(pub Vec<GameEvent>);
+
+// Where the camera is heading. x and y are the top-left corner of the view
+// in world pixels; the state places the actual camera between prev and now.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CameraFollow {
+    pub x: f32,
+    pub y: f32,
+    pub prev_x: f32,
+    pub prev_y: f32,
+    // Size of the view in world pixels, kept up to date by the state
+    pub view_width: f32,
+    pub view_height: f32,
+}
+
+// One thing to draw, pulled out of the world after each step so rendering
+// doesn't need to touch the components
+#[derive(Clone, Debug)]
+pub struct RenderItem {
+    pub sprite: Option<&'static str>,
+    // Clip name and frame, for animated sprites
+    pub animation: Option<(&'static str, usize)>,
+    pub x: f32,
+    pub y: f32,
+    pub prev_x: f32,
+    pub prev_y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub tint: [f32; 4],
+    pub flip_x: bool,
+    pub layer: u8,
+}
+
+impl RenderItem {
+    // Where to draw it `alpha` of the way from the previous step to the latest
+    pub fn interpolated_position(&self, alpha: f32) -> (f32, f32) {
+        (
+            self.prev_x + (self.x - self.prev_x) * alpha,
+            self.prev_y + (self.y - self.prev_y) * alpha,
+        )
+    }
+}
+
+// Everything to draw, bottom layer first
+#[derive(Default)]
+pub struct RenderList(pub Vec<RenderItem>);
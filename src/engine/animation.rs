@@ -0,0 +1,35 @@
+// A named run of frames inside a sprite sheet, e.g. "run" or "jump".
+// Frames are numbered left to right, top to bottom, starting at 0.
+#[derive(Clone, Debug)]
+pub struct AnimationClip {
+    pub first_frame: u32,
+    pub frame_count: u32,
+    // Looping clips wrap around, the others hold their last frame
+    pub looping: bool,
+}
+
+impl AnimationClip {
+    pub fn new(first_frame: u32, frame_count: u32, looping: bool) -> Self {
+        Self {
+            first_frame,
+            frame_count,
+            looping,
+        }
+    }
+
+    // The sheet frame to show after the clip has advanced `tick` times
+    pub fn frame_at(&self, tick: usize) -> u32 {
+        if self.frame_count == 0 {
+            return self.first_frame;
+        }
+
+        let count = self.frame_count as usize;
+        let offset = if self.looping {
+            tick % count
+        } else {
+            tick.min(count - 1)
+        };
+
+        self.first_frame + offset as u32
+    }
+}
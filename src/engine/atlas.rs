@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+// Where a sprite ended up inside the atlas
+#[derive(Clone, Copy, Debug)]
+pub struct AtlasRegion {
+    // Index of the page texture holding the sprite
+    pub page: usize,
+    // [u, v, width, height] within the page
+    pub uv: [f32; 4],
+    // Size of the original image in pixels
+    pub width: u32,
+    pub height: u32,
+}
+
+// The result of packing: page images ready to upload, plus a lookup by sprite id
+pub struct PackedAtlas {
+    pub page_size: u32,
+    pub pages: Vec<image::RgbaImage>,
+    pub regions: HashMap<String, AtlasRegion>,
+}
+
+// Collects sprite images and packs them into as few square pages as possible.
+// Images are placed on shelves (rows) from tallest to shortest, and a new page
+// is started whenever the current one is full.
+pub struct AtlasBuilder {
+    page_size: u32,
+    // Empty pixels kept around each image so neighbours never bleed into each other
+    padding: u32,
+    images: Vec<(String, image::RgbaImage)>,
+}
+
+impl AtlasBuilder {
+    pub fn new(page_size: u32) -> Self {
+        Self {
+            page_size,
+            padding: 1,
+            images: Vec::new(),
+        }
+    }
+
+    // Add an already decoded image
+    pub fn add_image(&mut self, id: &str, image: image::RgbaImage) {
+        self.images.push((id.to_string(), image));
+    }
+
+    // Decode and add an image file's contents
+    pub fn add_bytes(&mut self, id: &str, bytes: &[u8]) -> Result<(), String> {
+        let img = image::load_from_memory(bytes)
+            .map_err(|e| format!("Failed to load image {}: {}", id, e))?;
+        self.add_image(id, img.to_rgba8());
+        Ok(())
+    }
+
+    // Add every PNG in a directory, using the file name without extension as the id.
+    // Returns how many images were added.
+    pub fn add_dir(&mut self, dir: &Path) -> Result<usize, String> {
+        let entries = fs::read_dir(dir)
+            .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+
+        // Sort so sprites land in the same place on every run
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("png")))
+            .collect();
+        paths.sort();
+
+        for path in &paths {
+            let id = path.file_stem()
+                .and_then(|stem| stem.to_str())
+                .ok_or_else(|| format!("Invalid file name: {}", path.display()))?;
+            let bytes = fs::read(path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            self.add_bytes(id, &bytes)?;
+        }
+
+        Ok(paths.len())
+    }
+
+    // Pack everything added so far
+    pub fn build(self) -> Result<PackedAtlas, String> {
+        let page_size = self.page_size;
+        let padding = self.padding;
+
+        // Tallest first keeps shelves tight
+        let mut images = self.images;
+        images.sort_by(|a, b| b.1.height().cmp(&a.1.height()).then_with(|| a.0.cmp(&b.0)));
+
+        let mut pages: Vec<image::RgbaImage> = Vec::new();
+        let mut regions = HashMap::new();
+
+        // Packing cursor on the current page
+        let mut cursor_x = 0;
+        let mut cursor_y = 0;
+        let mut shelf_height = 0;
+
+        for (id, img) in images {
+            let (width, height) = img.dimensions();
+            let padded_width = width + padding * 2;
+            let padded_height = height + padding * 2;
+
+            if padded_width > page_size || padded_height > page_size {
+                return Err(format!(
+                    "Sprite {} ({}x{}) does not fit in a {}x{} atlas page",
+                    id, width, height, page_size, page_size
+                ));
+            }
+
+            // Move to the next shelf when this row is full
+            if cursor_x + padded_width > page_size {
+                cursor_x = 0;
+                cursor_y += shelf_height;
+                shelf_height = 0;
+            }
+
+            // Start a new page when we run out of shelves
+            if pages.is_empty() || cursor_y + padded_height > page_size {
+                pages.push(image::RgbaImage::new(page_size, page_size));
+                cursor_x = 0;
+                cursor_y = 0;
+                shelf_height = 0;
+            }
+
+            let page = pages.len() - 1;
+            let x = cursor_x + padding;
+            let y = cursor_y + padding;
+            image::imageops::replace(&mut pages[page], &img, x as i64, y as i64);
+
+            let size = page_size as f32;
+            regions.insert(id, AtlasRegion {
+                page,
+                uv: [
+                    x as f32 / size,
+                    y as f32 / size,
+                    width as f32 / size,
+                    height as f32 / size,
+                ],
+                width,
+                height,
+            });
+
+            cursor_x += padded_width;
+            shelf_height = shelf_height.max(padded_height);
+        }
+
+        Ok(PackedAtlas {
+            page_size,
+            pages,
+            regions,
+        })
+    }
+}
@@ -0,0 +1,54 @@
+use nalgebra as na;
+
+// An orthographic camera for the 2D world. Everything is measured in world
+// pixels with y pointing down, the same as level and player coordinates.
+pub struct Camera2D {
+    // World position at the centre of the view
+    pub position: na::Vector2<f32>,
+    // 1.0 shows one world pixel per screen pixel, 2.0 doubles everything in size
+    pub zoom: f32,
+    // Size of the render target in screen pixels
+    pub viewport_width: f32,
+    pub viewport_height: f32,
+}
+
+impl Camera2D {
+    pub fn new(viewport_width: f32, viewport_height: f32) -> Self {
+        Self {
+            position: na::Vector2::new(viewport_width / 2.0, viewport_height / 2.0),
+            zoom: 1.0,
+            viewport_width,
+            viewport_height,
+        }
+    }
+
+    // Call when the window is resized
+    pub fn set_viewport(&mut self, width: f32, height: f32) {
+        self.viewport_width = width;
+        self.viewport_height = height;
+    }
+
+    // How much of the world fits on screen at the current zoom
+    pub fn visible_size(&self) -> (f32, f32) {
+        (self.viewport_width / self.zoom, self.viewport_height / self.zoom)
+    }
+
+    // The world area on screen as (left, top, right, bottom)
+    pub fn visible_rect(&self) -> (f32, f32, f32, f32) {
+        let (width, height) = self.visible_size();
+        (
+            self.position.x - width / 2.0,
+            self.position.y - height / 2.0,
+            self.position.x + width / 2.0,
+            self.position.y + height / 2.0,
+        )
+    }
+
+    // Matrix that maps world pixels to clip space
+    pub fn view_projection(&self) -> na::Matrix4<f32> {
+        let (left, top, right, bottom) = self.visible_rect();
+
+        // World y grows downwards, so the bottom edge goes to -1 and the top edge to +1
+        na::Matrix4::new_orthographic(left, right, bottom, top, -1.0, 1.0)
+    }
+}
@@ -0,0 +1,135 @@
+use winit::window::Window;
+use wgpu::{Adapter, Device, Instance, Queue, Surface, SurfaceConfiguration};
+
+// Everything we need from wgpu, created exactly once. Windowed contexts also
+// own the window's surface; headless ones (tests, CI) don't have one.
+pub struct GpuContext {
+    pub instance: Instance,
+    pub adapter: Adapter,
+    pub device: Device,
+    pub queue: Queue,
+    pub surface: Option<Surface>,
+}
+
+impl GpuContext {
+    // Create a context that presents to `window`.
+    // The window must outlive the context, since the surface refers to it.
+    pub fn new(window: &Window) -> Result<Self, String> {
+        let instance = Self::create_instance();
+
+        let surface = unsafe { instance.create_surface(window) }
+            .map_err(|e| format!("Failed to create surface: {}", e))?;
+
+        let adapter = Self::request_adapter(&instance, Some(&surface), false)?;
+        let (device, queue) = Self::request_device(&adapter)?;
+
+        Ok(Self {
+            instance,
+            adapter,
+            device,
+            queue,
+            surface: Some(surface),
+        })
+    }
+
+    // Create a context without a window, for offscreen rendering. Prefers the
+    // software adapter so images come out the same on every machine.
+    pub fn headless() -> Result<Self, String> {
+        let instance = Self::create_instance();
+        let adapter = Self::request_adapter(&instance, None, true)?;
+        let (device, queue) = Self::request_device(&adapter)?;
+
+        Ok(Self {
+            instance,
+            adapter,
+            device,
+            queue,
+            surface: None,
+        })
+    }
+
+    fn create_instance() -> Instance {
+        wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            dx12_shader_compiler: Default::default(),
+        })
+    }
+
+    // Try a real GPU and the software fallback adapter (e.g. llvmpipe on headless
+    // Linux) in order of preference, rather than failing outright
+    fn request_adapter(instance: &Instance, 
+                       surface: Option<&Surface>, 
+                       prefer_software: bool) -> Result<Adapter, String> {
+        let order = if prefer_software { [true, false] } else { [false, true] };
+        order.iter().find_map(|&force_fallback_adapter| {
+            futures::executor::block_on(instance.request_adapter(
+                &wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::default(),
+                    compatible_surface: surface,
+                    force_fallback_adapter,
+                },
+            ))
+        }).ok_or_else(|| "Failed to find an appropriate adapter".to_string())
+    }
+
+    fn request_device(adapter: &Adapter) -> Result<(Device, Queue), String> {
+        // Software and older adapters often can't meet the default limits,
+        // so ask for the downlevel set scaled up to what the adapter supports
+        let supported = adapter.limits();
+        let limits = if wgpu::Limits::default().check_limits(&supported) {
+            wgpu::Limits::default()
+        } else {
+            wgpu::Limits::downlevel_webgl2_defaults().using_resolution(supported)
+        };
+
+        futures::executor::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                features: wgpu::Features::empty(),
+                limits,
+                label: None,
+            },
+            None,
+        )).map_err(|e| format!("Failed to create device: {}", e))
+    }
+
+    // The window surface. Panics on headless contexts, which have none.
+    pub fn surface(&self) -> &Surface {
+        self.surface.as_ref().expect("Headless GPU context has no surface")
+    }
+
+    // Name, backend and type of the adapter we ended up with
+    pub fn adapter_info(&self) -> wgpu::AdapterInfo {
+        self.adapter.get_info()
+    }
+
+    // The best limits the adapter supports
+    pub fn supported_limits(&self) -> wgpu::Limits {
+        self.adapter.limits()
+    }
+
+    // True when rendering on the CPU, e.g. the fallback adapter
+    pub fn is_software(&self) -> bool {
+        self.adapter_info().device_type == wgpu::DeviceType::Cpu
+    }
+
+    // A surface configuration for the given size, preferring an sRGB format.
+    // Returns None for headless contexts.
+    pub fn surface_config(&self, width: u32, height: u32) -> Option<SurfaceConfiguration> {
+        let surface = self.surface.as_ref()?;
+        let surface_caps = surface.get_capabilities(&self.adapter);
+        let surface_format = surface_caps.formats.iter()
+            .copied()
+            .find(|f| f.is_srgb())
+            .unwrap_or(surface_caps.formats[0]);
+
+        Some(wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: surface_format,
+            width,
+            height,
+            present_mode: surface_caps.present_modes[0],
+            alpha_mode: surface_caps.alpha_modes[0],
+            view_formats: vec![],
+        })
+    }
+}
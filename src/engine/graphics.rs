@@ -1,438 +1,1183 @@
-use wgpu::{Device, Queue, Surface, SurfaceConfiguration, TextureView};
-use std::time::Instant;
-use std::collections::HashMap;
-use image::GenericImageView;
-
-// A simple struct to help with timing
-pub struct Timer {
-    last_instant: Instant,
-}
-
-impl Timer {
-    pub fn new() -> Self {
-        Self {
-            last_instant: Instant::now(),
-        }
-    }
-    
-    // Calculate the delta time since the last call
-    pub fn delta(&mut self) -> f32 {
-        let now = Instant::now();
-        let dt = now - self.last_instant;
-        self.last_instant = now;
-        dt.as_secs_f32()
-    }
-}
-
-// Represents a loaded texture
-pub struct Texture {
-    pub texture: wgpu::Texture,
-    pub view: wgpu::TextureView,
-    pub sampler: wgpu::Sampler,
-    pub width: u32,
-    pub height: u32,
-    pub frame_width: u32,
-    pub frame_height: u32,
-    pub frames_per_row: u32,
-    pub total_frames: u32,
-}
-
-impl Texture {
-    // Create a texture from image bytes
-    pub fn from_bytes(
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
-        bytes: &[u8],
-        label: &str,
-        frame_width: Option<u32>,
-        frame_height: Option<u32>,
-        total_frames: Option<u32>,
-    ) -> Result<Self, String> {
-        // Load the image
-        let img = match image::load_from_memory(bytes) {
-            Ok(img) => img,
-            Err(e) => return Err(format!("Failed to load image: {}", e)),
-        };
-        let rgba = img.to_rgba8();
-        let dimensions = img.dimensions();
-        
-        // Create the texture
-        let size = wgpu::Extent3d {
-            width: dimensions.0,
-            height: dimensions.1,
-            depth_or_array_layers: 1,
-        };
-
-        // frame info
-
-        let frame_width = frame_width.unwrap_or(dimensions.0);
-        let frame_height = frame_height.unwrap_or(dimensions.1);
-        let frames_per_row = dimensions.0 / frame_width;
-        let total_frames = total_frames.unwrap_or(
-            (dimensions.0 / frame_width) * (dimensions.1 / frame_height)
-        );
-
-        let texture = device.create_texture(
-            &wgpu::TextureDescriptor {
-                label: Some(label),
-                size,
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba8UnormSrgb,
-                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-                view_formats: &[],
-            }
-        );
-        
-        // Upload the image data to the texture
-        queue.write_texture(
-            wgpu::ImageCopyTexture {
-                texture: &texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            &rgba,
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(4 * dimensions.0),
-                rows_per_image: Some(dimensions.1),
-            },
-            size,
-        );
-        
-        // Create the texture view and sampler
-        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Nearest, // Pixel art style
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            ..Default::default()
-        });
-        
-        Ok(Self {
-            texture,
-            view,
-            sampler,
-            width: dimensions.0,
-            height: dimensions.1,
-            frame_width,
-            frame_height,
-            frames_per_row,
-            total_frames,
-        })
-    }
-}
-
-// A vertex for our sprites
-#[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
-pub struct Vertex {
-    position: [f32; 3],
-    tex_coords: [f32; 2],
-}
-
-impl Vertex {
-    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
-        wgpu::VertexBufferLayout {
-            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[
-                wgpu::VertexAttribute {
-                    offset: 0,
-                    shader_location: 0,
-                    format: wgpu::VertexFormat::Float32x3,
-                },
-                wgpu::VertexAttribute {
-                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
-                    shader_location: 1,
-                    format: wgpu::VertexFormat::Float32x2,
-                },
-            ],
-        }
-    }
-}
-
-// Our rendering manager
-pub struct Renderer {
-    render_pipeline: wgpu::RenderPipeline,
-    textures: HashMap<String, Texture>,
-    vertex_buffer: wgpu::Buffer,
-    index_buffer: wgpu::Buffer,
-    bind_group_layout: wgpu::BindGroupLayout,
-    texture_bind_groups: HashMap<String, wgpu::BindGroup>,
-}
-
-impl Renderer {
-    pub fn new(device: &Device) -> Self {
-        // Load shader
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/sprite.wgsl").into()),
-        });
-        
-        // Create bind group layout for textures
-        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        multisampled: false,
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                    count: None,
-                },
-            ],
-            label: Some("texture_bind_group_layout"),
-        });
-        
-        // Create pipeline layout
-        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("Render Pipeline Layout"),
-            bind_group_layouts: &[&bind_group_layout],
-            push_constant_ranges: &[],
-        });
-        
-        // Create render pipeline
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[Vertex::desc()],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: wgpu::TextureFormat::Bgra8UnormSrgb, // Adjust format to match your surface
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
-        });
-        
-        // Create a quad mesh for sprites
-        let vertices = [
-            // Position            // Texture coords
-            Vertex { position: [-0.5, -0.5, 0.0], tex_coords: [0.0, 1.0] },
-            Vertex { position: [ 0.5, -0.5, 0.0], tex_coords: [1.0, 1.0] },
-            Vertex { position: [ 0.5,  0.5, 0.0], tex_coords: [1.0, 0.0] },
-            Vertex { position: [-0.5,  0.5, 0.0], tex_coords: [0.0, 0.0] },
-        ];
-        let indices = [
-            0, 1, 2,
-            0, 2, 3,
-        ];
-        
-        // Create vertex and index buffers
-        let vertex_buffer = device.create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: Some("Vertex Buffer"),
-                contents: bytemuck::cast_slice(&vertices),
-                usage: wgpu::BufferUsages::VERTEX,
-            }
-        );
-        let index_buffer = device.create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: Some("Index Buffer"),
-                contents: bytemuck::cast_slice(&indices),
-                usage: wgpu::BufferUsages::INDEX,
-            }
-        );
-        
-        Self {
-            render_pipeline,
-            textures: HashMap::new(),
-            vertex_buffer,
-            index_buffer,
-            bind_group_layout,
-            texture_bind_groups: HashMap::new(),
-        }
-    }
-    
-    pub fn new_empty() -> Self {
-        // This is a temporary placeholder
-        // We'll replace it with a proper implementation later
-        unimplemented!("Cannot create a Renderer without a device. This is a placeholder.")
-    }
-    
-    // Load a texture from bytes
-    pub fn load_texture(&mut self, device: &Device, queue: &Queue, id: &str, bytes: &[u8]) -> Result<(), String> {
-        let texture = Texture::from_bytes(device, queue, bytes, id)?;
-        
-        // Create a bind group for this texture
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &self.bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&texture.view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&texture.sampler),
-                },
-            ],
-            label: Some(&format!("{}_bind_group", id)),
-        });
-        
-        // Store the texture and bind group
-        self.textures.insert(id.to_string(), texture);
-        self.texture_bind_groups.insert(id.to_string(), bind_group);
-        
-        Ok(())
-    }
-    
-    // Begin a new frame
-    pub fn begin_frame(&mut self, surface: &Surface) -> Result<wgpu::SurfaceTexture, wgpu::SurfaceError> {
-        surface.get_current_texture()
-    }
-    
-    // End the frame and present it
-    pub fn end_frame(&mut self, frame: wgpu::SurfaceTexture) {
-        frame.present();
-    }
-    
-    // Clear the screen with a color
-    pub fn clear_screen(&self, frame: &wgpu::SurfaceTexture, device: &Device, queue: &Queue, color: wgpu::Color) -> wgpu::TextureView {
-        let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
-        
-        let mut encoder = device.create_command_encoder(
-            &wgpu::CommandEncoderDescriptor { label: Some("Render Encoder") }
-        );
-        
-        {
-            let _render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(color),
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: None,
-            });
-        }
-        
-        queue.submit(std::iter::once(encoder.finish()));
-        
-        view
-    }
-    
-    // Draw a sprite
-    pub fn draw_sprite(&self, 
-                      device: &Device, 
-                      queue: &Queue, 
-                      view: &TextureView, 
-                      texture_id: &str, 
-                      x: f32, 
-                      y: f32, 
-                      width: f32, 
-                      height: f32) {
-        // Skip if the texture doesn't exist
-        if !self.texture_bind_groups.contains_key(texture_id) {
-            return;
-        }
-        
-        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-            label: Some("Sprite Encoder"),
-        });
-        
-        // Model matrix for position and scale
-        let model_matrix = [
-            [width, 0.0, 0.0, 0.0],
-            [0.0, height, 0.0, 0.0],
-            [0.0, 0.0, 1.0, 0.0],
-            [x, y, 0.0, 1.0],
-        ];
-        
-        let model_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Model Matrix Buffer"),
-            contents: bytemuck::cast_slice(&model_matrix),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-        
-        let model_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-            ],
-            label: Some("model_bind_group_layout"),
-        });
-        
-        let model_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &model_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: model_buffer.as_entire_binding(),
-                },
-            ],
-            label: Some("model_bind_group"),
-        });
-        
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Load, // Don't clear, we already did that
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: None,
-            });
-            
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_bind_group(0, &self.texture_bind_groups[texture_id], &[]);
-            render_pass.set_bind_group(1, &model_bind_group, &[]);
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-            render_pass.draw_indexed(0..6, 0, 0..1);
-        }
-        
-        queue.submit(std::iter::once(encoder.finish()));
-    }
-}
\ No newline at end of file
+use wgpu::{Device, Queue, TextureView};
+use wgpu::util::DeviceExt;
+use std::time::Instant;
+use std::collections::HashMap;
+use std::ops::Range;
+use super::camera::Camera2D;
+use super::animation::AnimationClip;
+use super::atlas::PackedAtlas;
+use super::text::{layout_text, GlyphAtlas, TextLayout, TextSpan, TextStyle};
+
+// A simple struct to help with timing
+pub struct Timer {
+    last_instant: Instant,
+}
+
+impl Default for Timer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Timer {
+    pub fn new() -> Self {
+        Self {
+            last_instant: Instant::now(),
+        }
+    }
+    
+    // Calculate the delta time since the last call
+    pub fn delta(&mut self) -> f32 {
+        let now = Instant::now();
+        let dt = now - self.last_instant;
+        self.last_instant = now;
+        dt.as_secs_f32()
+    }
+}
+
+// Represents a loaded texture
+pub struct Texture {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Texture {
+    // Create a texture from image bytes
+    pub fn from_bytes(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bytes: &[u8],
+        label: &str,
+    ) -> Result<Self, String> {
+        // Load the image
+        let img = match image::load_from_memory(bytes) {
+            Ok(img) => img,
+            Err(e) => return Err(format!("Failed to load image: {}", e)),
+        };
+        Ok(Self::from_image(device, queue, &img.to_rgba8(), label))
+    }
+    
+    // Create a texture from an already decoded image
+    pub fn from_image(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        rgba: &image::RgbaImage,
+        label: &str,
+    ) -> Self {
+        let dimensions = rgba.dimensions();
+        
+        // Create the texture
+        let size = wgpu::Extent3d {
+            width: dimensions.0,
+            height: dimensions.1,
+            depth_or_array_layers: 1,
+        };
+
+        let texture = device.create_texture(
+            &wgpu::TextureDescriptor {
+                label: Some(label),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            }
+        );
+        
+        // Upload the image data to the texture
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            rgba,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * dimensions.0),
+                rows_per_image: Some(dimensions.1),
+            },
+            size,
+        );
+        
+        // Create the texture view and sampler
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest, // Pixel art style
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        
+        Self {
+            texture,
+            view,
+            sampler,
+            width: dimensions.0,
+            height: dimensions.1,
+        }
+    }
+}
+
+// A named image the renderer can draw: a rectangle on one of its texture pages,
+// optionally split into equally sized animation frames
+pub struct SpriteInfo {
+    pub page: usize,
+    // [u, v, width, height] of the whole image within the page
+    pub uv: [f32; 4],
+    pub width: u32,
+    pub height: u32,
+    pub frame_width: u32,
+    pub frame_height: u32,
+    pub frames_per_row: u32,
+    pub total_frames: u32,
+    // Named animations that play frames from this sheet
+    pub clips: HashMap<String, AnimationClip>,
+}
+
+impl SpriteInfo {
+    // A single-frame sprite covering `uv` on `page`
+    fn new(page: usize, uv: [f32; 4], width: u32, height: u32) -> Self {
+        Self {
+            page,
+            uv,
+            width,
+            height,
+            frame_width: width,
+            frame_height: height,
+            frames_per_row: 1,
+            total_frames: 1,
+            clips: HashMap::new(),
+        }
+    }
+    
+    // Split the sprite into frames of the given size, read left to right, top to bottom
+    fn set_frames(&mut self, frame_width: u32, frame_height: u32, total_frames: Option<u32>) {
+        let frame_width = frame_width.clamp(1, self.width.max(1));
+        let frame_height = frame_height.clamp(1, self.height.max(1));
+        self.frame_width = frame_width;
+        self.frame_height = frame_height;
+        self.frames_per_row = self.width / frame_width;
+        self.total_frames = total_frames.unwrap_or(self.frames_per_row * (self.height / frame_height));
+    }
+    
+    // Page texture coordinates of a frame as [u, v, width, height]
+    pub fn frame_uv(&self, frame: u32) -> [f32; 4] {
+        let frame = frame % self.total_frames.max(1);
+        let frames_per_row = self.frames_per_row.max(1);
+        let column = frame % frames_per_row;
+        let row = frame / frames_per_row;
+        
+        // Frame sizes are in pixels, so scale them into this sprite's part of the page
+        let u_per_pixel = self.uv[2] / self.width.max(1) as f32;
+        let v_per_pixel = self.uv[3] / self.height.max(1) as f32;
+        let u_size = self.frame_width as f32 * u_per_pixel;
+        let v_size = self.frame_height as f32 * v_per_pixel;
+        [
+            self.uv[0] + column as f32 * u_size,
+            self.uv[1] + row as f32 * v_size,
+            u_size,
+            v_size,
+        ]
+    }
+}
+
+// A texture we can render into without a window, then read back to the CPU
+pub struct OffscreenTarget {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub width: u32,
+    pub height: u32,
+    pub format: wgpu::TextureFormat,
+}
+
+impl OffscreenTarget {
+    pub fn new(device: &Device, width: u32, height: u32, format: wgpu::TextureFormat) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Target"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        
+        Self {
+            texture,
+            view,
+            width,
+            height,
+            format,
+        }
+    }
+    
+    // Copy the rendered pixels back into an image. Blocks until the GPU is done.
+    pub fn read_pixels(&self, device: &Device, queue: &Queue) -> Result<image::RgbaImage, String> {
+        let bytes_per_pixel = 4;
+        let unpadded_bytes_per_row = self.width * bytes_per_pixel;
+        
+        // Buffer copies need every row to start on a 256 byte boundary
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+        
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Offscreen Readback Buffer"),
+            size: (padded_bytes_per_row * self.height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Offscreen Readback Encoder"),
+        });
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(self.height),
+                },
+            },
+            wgpu::Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit(std::iter::once(encoder.finish()));
+        
+        // Map the buffer and wait for it
+        let slice = buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        receiver.recv()
+            .map_err(|e| format!("Readback was cancelled: {}", e))?
+            .map_err(|e| format!("Failed to map readback buffer: {}", e))?;
+        
+        // Strip the row padding and make sure channels end up in RGBA order
+        let swap_red_blue = matches!(
+            self.format,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        );
+        let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * self.height) as usize);
+        {
+            let data = slice.get_mapped_range();
+            for row in data.chunks(padded_bytes_per_row as usize) {
+                for pixel in row[..unpadded_bytes_per_row as usize].chunks(4) {
+                    if swap_red_blue {
+                        pixels.extend_from_slice(&[pixel[2], pixel[1], pixel[0], pixel[3]]);
+                    } else {
+                        pixels.extend_from_slice(pixel);
+                    }
+                }
+            }
+        }
+        buffer.unmap();
+        
+        image::RgbaImage::from_raw(self.width, self.height, pixels)
+            .ok_or_else(|| "Readback size did not match the target".to_string())
+    }
+}
+
+// A vertex for our sprites
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Vertex {
+    position: [f32; 3],
+    tex_coords: [f32; 2],
+}
+
+impl Vertex {
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+            ],
+        }
+    }
+}
+
+// Per-sprite data, uploaded once per frame into the instance buffer
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SpriteInstance {
+    position: [f32; 2],
+    size: [f32; 2],
+    // Sub-rectangle of the texture to sample, in UV space
+    uv_offset: [f32; 2],
+    uv_size: [f32; 2],
+    // Multiplied with the sampled colour
+    tint: [f32; 4],
+}
+
+impl SpriteInstance {
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<SpriteInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 6]>() as wgpu::BufferAddress,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+// Per-sprite effects that don't need their own texture
+#[derive(Clone, Copy, Debug)]
+pub struct SpriteOptions {
+    // Mirror the sprite left-to-right / top-to-bottom
+    pub flip_x: bool,
+    pub flip_y: bool,
+    // RGBA multiplied with the texture; the alpha channel sets opacity
+    pub tint: [f32; 4],
+}
+
+impl Default for SpriteOptions {
+    fn default() -> Self {
+        Self {
+            flip_x: false,
+            flip_y: false,
+            tint: [1.0, 1.0, 1.0, 1.0],
+        }
+    }
+}
+
+// Where a sprite goes: its centre and size, in pixels of the current space
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpriteRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl SpriteRect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self { x, y, width, height }
+    }
+}
+
+// How a sprite sheet is cut up into equally sized animation frames
+#[derive(Clone, Copy, Debug)]
+pub struct FrameLayout {
+    pub frame_width: u32,
+    pub frame_height: u32,
+    // Defaults to every whole frame that fits on the sheet
+    pub total_frames: Option<u32>,
+}
+
+// The camera's view-projection matrix as the shader sees it
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+}
+
+impl CameraUniform {
+    fn from_camera(camera: &Camera2D) -> Self {
+        Self {
+            view_proj: camera.view_projection().into(),
+        }
+    }
+}
+
+// Which coordinate system pushed sprites are in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Space {
+    // World pixels, seen through the camera
+    World,
+    // Screen pixels from the top-left of the window, for HUD and dialogue
+    Screen,
+}
+
+// A font that has been uploaded to the renderer's pages
+struct LoadedFont {
+    glyph_atlas: GlyphAtlas,
+    // Page and UV rect of each glyph bitmap
+    glyph_uvs: HashMap<char, (usize, [f32; 4])>,
+}
+
+// A run of consecutive instances that all sample the same texture page
+struct SpriteBatch {
+    page: usize,
+    space: Space,
+    instances: Range<u32>,
+}
+
+// Handle to sprites that were uploaded once and are kept on the GPU between
+// frames, e.g. a chunk of tilemap. Queue them with push_static_batch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct StaticBatchId(u64);
+
+// Instances that live in their own buffer, split into page runs like the frame batch
+struct StaticBatch {
+    // None when nothing was pushed, since empty buffers can't be bound
+    buffer: Option<wgpu::Buffer>,
+    runs: Vec<SpriteBatch>,
+}
+
+// Something to draw during flush, in the order it was queued
+enum DrawCommand {
+    // A run of sprites from this frame's instance buffer
+    Sprites(SpriteBatch),
+    Static(StaticBatchId),
+}
+
+// How many sprites the instance buffer can hold before it has to grow
+const INITIAL_INSTANCE_CAPACITY: usize = 1024;
+
+// Our rendering manager
+pub struct Renderer {
+    render_pipeline: wgpu::RenderPipeline,
+    format: wgpu::TextureFormat,
+    // Kept so the pipeline can be rebuilt for a different surface format
+    shader: wgpu::ShaderModule,
+    pipeline_layout: wgpu::PipelineLayout,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    bind_group_layout: wgpu::BindGroupLayout,
+    
+    // Texture pages (atlas pages or standalone textures) and the sprites that live on them
+    pages: Vec<Texture>,
+    page_bind_groups: Vec<wgpu::BindGroup>,
+    sprites: HashMap<String, SpriteInfo>,
+    camera_buffer: wgpu::Buffer,
+    camera_bind_group: wgpu::BindGroup,
+    screen_buffer: wgpu::Buffer,
+    screen_bind_group: wgpu::BindGroup,
+    
+    // A single white pixel, tinted to draw solid rectangles
+    white_page: usize,
+    fonts: HashMap<String, LoadedFont>,
+    
+    // Sprites queued since the last begin_batch
+    instances: Vec<SpriteInstance>,
+    commands: Vec<DrawCommand>,
+    instance_buffer: wgpu::Buffer,
+    instance_capacity: usize,
+    space: Space,
+    
+    // Geometry that stays uploaded until it is removed
+    static_batches: HashMap<StaticBatchId, StaticBatch>,
+    next_static_id: u64,
+}
+
+impl Renderer {
+    // `format` must be the format of the surface (or texture) the renderer draws into
+    pub fn new(device: &Device, queue: &Queue, format: wgpu::TextureFormat) -> Self {
+        // Load shader
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/sprite.wgsl").into()),
+        });
+        
+        // Create bind group layout for textures
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("texture_bind_group_layout"),
+        });
+        
+        // Create the camera uniform; it starts out as a 1:1 pixel view of a 800x600 window
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::cast_slice(&[CameraUniform::from_camera(&Camera2D::new(800.0, 600.0))]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        
+        let camera_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("camera_bind_group_layout"),
+        });
+        
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &camera_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: camera_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("camera_bind_group"),
+        });
+        
+        // Screen space gets its own fixed camera with the origin at the top-left of the window
+        let screen_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Screen Camera Buffer"),
+            contents: bytemuck::cast_slice(&[CameraUniform::from_camera(&Camera2D::new(800.0, 600.0))]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        
+        let screen_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &camera_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: screen_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("screen_bind_group"),
+        });
+        
+        // Create pipeline layout
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Render Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout, &camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        
+        // Create render pipeline
+        let render_pipeline = Self::create_pipeline(device, &shader, &pipeline_layout, format);
+        
+        // Create a quad mesh for sprites
+        let vertices = [
+            // Position            // Texture coords
+            // y points down in the world, so the top row of the image is at -0.5
+            Vertex { position: [-0.5, -0.5, 0.0], tex_coords: [0.0, 0.0] },
+            Vertex { position: [ 0.5, -0.5, 0.0], tex_coords: [1.0, 0.0] },
+            Vertex { position: [ 0.5,  0.5, 0.0], tex_coords: [1.0, 1.0] },
+            Vertex { position: [-0.5,  0.5, 0.0], tex_coords: [0.0, 1.0] },
+        ];
+        let indices: [u16; 6] = [
+            0, 1, 2,
+            0, 2, 3,
+        ];
+        
+        // Create vertex and index buffers
+        let vertex_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Vertex Buffer"),
+                contents: bytemuck::cast_slice(&vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            }
+        );
+        let index_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Index Buffer"),
+                contents: bytemuck::cast_slice(&indices),
+                usage: wgpu::BufferUsages::INDEX,
+            }
+        );
+        
+        let instance_buffer = Self::create_instance_buffer(device, INITIAL_INSTANCE_CAPACITY);
+        
+        let mut renderer = Self {
+            render_pipeline,
+            format,
+            shader,
+            pipeline_layout,
+            vertex_buffer,
+            index_buffer,
+            bind_group_layout,
+            pages: Vec::new(),
+            page_bind_groups: Vec::new(),
+            sprites: HashMap::new(),
+            camera_buffer,
+            camera_bind_group,
+            screen_buffer,
+            screen_bind_group,
+            white_page: 0,
+            fonts: HashMap::new(),
+            instances: Vec::with_capacity(INITIAL_INSTANCE_CAPACITY),
+            commands: Vec::new(),
+            instance_buffer,
+            instance_capacity: INITIAL_INSTANCE_CAPACITY,
+            space: Space::World,
+            static_batches: HashMap::new(),
+            next_static_id: 0,
+        };
+        
+        let white = image::RgbaImage::from_pixel(1, 1, image::Rgba([255, 255, 255, 255]));
+        renderer.white_page = renderer.pages.len();
+        let white_texture = Texture::from_image(device, queue, &white, "white");
+        renderer.add_page(device, white_texture);
+        
+        renderer
+    }
+    
+    fn create_pipeline(device: &Device, 
+                       shader: &wgpu::ShaderModule, 
+                       pipeline_layout: &wgpu::PipelineLayout, 
+                       format: wgpu::TextureFormat) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipeline"),
+            layout: Some(pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::desc(), SpriteInstance::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None, // The camera flips y, so quads can face either way
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        })
+    }
+    
+    // Rebuild the pipeline if the surface was configured with a different format
+    pub fn set_format(&mut self, device: &Device, format: wgpu::TextureFormat) {
+        if format != self.format {
+            self.render_pipeline = Self::create_pipeline(device, &self.shader, &self.pipeline_layout, format);
+            self.format = format;
+        }
+    }
+    
+    fn create_instance_buffer(device: &Device, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Sprite Instance Buffer"),
+            size: (capacity * std::mem::size_of::<SpriteInstance>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+    
+    // Load a texture from bytes
+    pub fn load_texture(&mut self, device: &Device, queue: &Queue, id: &str, bytes: &[u8]) -> Result<(), String> {
+        self.load_sprite_sheet(device, queue, id, bytes, None)
+    }
+    
+    // Load a texture that is split into equally sized animation frames.
+    // Without a layout the whole texture is one frame.
+    pub fn load_sprite_sheet(&mut self, 
+                             device: &Device, 
+                             queue: &Queue, 
+                             id: &str, 
+                             bytes: &[u8], 
+                             frames: Option<FrameLayout>) -> Result<(), String> {
+        let texture = Texture::from_bytes(device, queue, bytes, id)?;
+        
+        let mut sprite = SpriteInfo::new(self.pages.len(), [0.0, 0.0, 1.0, 1.0], texture.width, texture.height);
+        if let Some(frames) = frames {
+            sprite.set_frames(frames.frame_width, frames.frame_height, frames.total_frames);
+        }
+        
+        self.add_page(device, texture);
+        self.sprites.insert(id.to_string(), sprite);
+        
+        Ok(())
+    }
+    
+    // Upload the pages of a packed atlas and register every sprite in it.
+    // Sprites on the same page can be drawn without switching bind groups.
+    pub fn load_atlas(&mut self, device: &Device, queue: &Queue, atlas: &PackedAtlas) {
+        let first_page = self.pages.len();
+        
+        for (i, page) in atlas.pages.iter().enumerate() {
+            let texture = Texture::from_image(device, queue, page, &format!("atlas_page_{}", first_page + i));
+            self.add_page(device, texture);
+        }
+        
+        for (id, region) in &atlas.regions {
+            let sprite = SpriteInfo::new(first_page + region.page, region.uv, region.width, region.height);
+            self.sprites.insert(id.clone(), sprite);
+        }
+    }
+    
+    // Split an already loaded sprite into animation frames
+    pub fn set_frames(&mut self, 
+                      id: &str, 
+                      frame_width: u32, 
+                      frame_height: u32, 
+                      total_frames: Option<u32>) -> Result<(), String> {
+        let sprite = self.sprites.get_mut(id)
+            .ok_or_else(|| format!("Unknown sprite: {}", id))?;
+        sprite.set_frames(frame_width, frame_height, total_frames);
+        Ok(())
+    }
+    
+    // Store a texture as a new page and create its bind group
+    fn add_page(&mut self, device: &Device, texture: Texture) {
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&texture.sampler),
+                },
+            ],
+            label: Some(&format!("page_{}_bind_group", self.pages.len())),
+        });
+        
+        self.pages.push(texture);
+        self.page_bind_groups.push(bind_group);
+    }
+    
+    // Define a named animation for a loaded texture
+    pub fn add_clip(&mut self, sprite_id: &str, name: &str, clip: AnimationClip) -> Result<(), String> {
+        let sprite = self.sprites.get_mut(sprite_id)
+            .ok_or_else(|| format!("Unknown sprite: {}", sprite_id))?;
+        sprite.clips.insert(name.to_string(), clip);
+        Ok(())
+    }
+    
+    // Rasterize a font and upload its glyphs so push_text can use it
+    pub fn load_font(&mut self, device: &Device, queue: &Queue, id: &str, bytes: &[u8], px: f32) -> Result<(), String> {
+        let glyph_atlas = GlyphAtlas::from_ttf(bytes, px)?;
+        
+        let first_page = self.pages.len();
+        for (i, page) in glyph_atlas.atlas.pages.iter().enumerate() {
+            let texture = Texture::from_image(device, queue, page, &format!("{}_glyphs_{}", id, i));
+            self.add_page(device, texture);
+        }
+        
+        let glyph_uvs = glyph_atlas.atlas.regions.iter()
+            .filter_map(|(key, region)| {
+                key.chars().next().map(|ch| (ch, (first_page + region.page, region.uv)))
+            })
+            .collect();
+        
+        self.fonts.insert(id.to_string(), LoadedFont { glyph_atlas, glyph_uvs });
+        Ok(())
+    }
+    
+    // Upload the camera used to project world-space sprites.
+    // The screen-space projection follows the camera's viewport size.
+    pub fn set_camera(&self, queue: &Queue, camera: &Camera2D) {
+        queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&[CameraUniform::from_camera(camera)]));
+        
+        let screen = Camera2D::new(camera.viewport_width, camera.viewport_height);
+        queue.write_buffer(&self.screen_buffer, 0, bytemuck::cast_slice(&[CameraUniform::from_camera(&screen)]));
+    }
+    
+    // Start collecting sprites for a new batch, dropping anything queued before
+    pub fn begin_batch(&mut self) {
+        self.instances.clear();
+        self.commands.clear();
+        self.space = Space::World;
+    }
+    
+    // Upload sprites once and keep them for later frames. `build` pushes sprites
+    // the usual way (push_sprite, push_rect, ...) but they go into the new static
+    // batch instead of the current frame, which is left untouched.
+    pub fn create_static_batch(&mut self, 
+                               device: &Device, 
+                               build: impl FnOnce(&mut Self)) -> StaticBatchId {
+        let frame_instances = std::mem::take(&mut self.instances);
+        let frame_commands = std::mem::take(&mut self.commands);
+        let frame_space = self.space;
+        self.space = Space::World;
+        
+        build(self);
+        
+        let instances = std::mem::replace(&mut self.instances, frame_instances);
+        let commands = std::mem::replace(&mut self.commands, frame_commands);
+        self.space = frame_space;
+        
+        // Static batches can't contain other static batches
+        let runs: Vec<SpriteBatch> = commands.into_iter()
+            .filter_map(|command| match command {
+                DrawCommand::Sprites(run) => Some(run),
+                DrawCommand::Static(_) => None,
+            })
+            .collect();
+        
+        let buffer = if instances.is_empty() {
+            None
+        } else {
+            Some(device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Static Sprite Buffer"),
+                contents: bytemuck::cast_slice(&instances),
+                usage: wgpu::BufferUsages::VERTEX,
+            }))
+        };
+        
+        let id = StaticBatchId(self.next_static_id);
+        self.next_static_id += 1;
+        self.static_batches.insert(id, StaticBatch { buffer, runs });
+        id
+    }
+    
+    // Free a static batch's GPU buffer. Unknown ids are ignored.
+    pub fn remove_static_batch(&mut self, id: StaticBatchId) {
+        self.static_batches.remove(&id);
+    }
+    
+    // Queue a static batch to be drawn this frame, in order with the other sprites
+    pub fn push_static_batch(&mut self, id: StaticBatchId) {
+        if self.static_batches.contains_key(&id) {
+            self.commands.push(DrawCommand::Static(id));
+        }
+    }
+    
+    // Choose the coordinate system for the sprites pushed after this
+    pub fn set_space(&mut self, space: Space) {
+        self.space = space;
+    }
+    
+    // Queue a solid rectangle. x and y are the top-left corner.
+    pub fn push_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: [f32; 4]) {
+        let options = SpriteOptions {
+            tint: color,
+            ..SpriteOptions::default()
+        };
+        self.push_quad(self.white_page, [0.0, 0.0, 1.0, 1.0], SpriteRect::new(x + width / 2.0, y + height / 2.0, width, height), options);
+    }
+    
+    // Lay out text without drawing it, e.g. to size a box around it
+    pub fn measure_text(&self, font_id: &str, spans: &[TextSpan], style: &TextStyle) -> Option<TextLayout> {
+        self.fonts.get(font_id).map(|font| layout_text(&font.glyph_atlas, spans, style))
+    }
+    
+    // Queue coloured, wrapped text with the top-left of its box at x, y.
+    // Returns the size of the laid out text.
+    pub fn push_text(&mut self, 
+                     font_id: &str, 
+                     spans: &[TextSpan], 
+                     x: f32, 
+                     y: f32, 
+                     style: &TextStyle) -> (f32, f32) {
+        let font = match self.fonts.get(font_id) {
+            Some(font) => font,
+            None => return (0.0, 0.0),
+        };
+        let layout = layout_text(&font.glyph_atlas, spans, style);
+        
+        // Look up every glyph first so the font isn't borrowed while pushing
+        let quads: Vec<_> = layout.glyphs.iter()
+            .filter_map(|glyph| font.glyph_uvs.get(&glyph.ch).map(|&(page, uv)| (page, uv, *glyph)))
+            .collect();
+        
+        for (page, uv, glyph) in quads {
+            let options = SpriteOptions {
+                tint: glyph.color,
+                ..SpriteOptions::default()
+            };
+            // Snap to whole pixels so glyphs stay crisp
+            let center_x = (x + glyph.x).round() + glyph.width / 2.0;
+            let center_y = (y + glyph.y).round() + glyph.height / 2.0;
+            self.push_quad(page, uv, SpriteRect::new(center_x, center_y, glyph.width, glyph.height), options);
+        }
+        
+        (layout.width, layout.height)
+    }
+    
+    // Queue a sprite for the current batch. x and y are the sprite's centre in world pixels.
+    // Nothing is sent to the GPU until flush.
+    pub fn push_sprite(&mut self, 
+                       sprite_id: &str, 
+                       x: f32, 
+                       y: f32, 
+                       width: f32, 
+                       height: f32) {
+        self.push_sprite_with(sprite_id, x, y, width, height, SpriteOptions::default());
+    }
+    
+    // Queue a whole-texture sprite with flipping or tinting applied
+    pub fn push_sprite_with(&mut self, 
+                            sprite_id: &str, 
+                            x: f32, 
+                            y: f32, 
+                            width: f32, 
+                            height: f32, 
+                            options: SpriteOptions) {
+        let (page, uv) = match self.sprites.get(sprite_id) {
+            Some(sprite) => (sprite.page, sprite.uv),
+            None => return,
+        };
+        self.push_quad(page, uv, SpriteRect::new(x, y, width, height), options);
+    }
+    
+    // Queue a single frame of a sprite sheet
+    pub fn push_sprite_frame(&mut self, 
+                             sprite_id: &str, 
+                             frame: u32, 
+                             rect: SpriteRect, 
+                             options: SpriteOptions) {
+        let (page, uv) = match self.sprites.get(sprite_id) {
+            Some(sprite) => (sprite.page, sprite.frame_uv(frame)),
+            None => return,
+        };
+        self.push_quad(page, uv, rect, options);
+    }
+    
+    // Queue the current frame of a named clip. `tick` is how many frames the clip has advanced.
+    // Textures without that clip just show their first frame.
+    pub fn push_animation(&mut self, 
+                          sprite_id: &str, 
+                          clip_name: &str, 
+                          tick: usize, 
+                          rect: SpriteRect, 
+                          options: SpriteOptions) {
+        let frame = match self.sprites.get(sprite_id) {
+            Some(sprite) => sprite.clips.get(clip_name).map_or(0, |clip| clip.frame_at(tick)),
+            None => return,
+        };
+        self.push_sprite_frame(sprite_id, frame, rect, options);
+    }
+    
+    fn push_quad(&mut self, 
+                 page: usize, 
+                 uv: [f32; 4], 
+                 rect: SpriteRect, 
+                 options: SpriteOptions) {
+        // Flipping is just sampling the frame from the opposite edge
+        let [mut u, mut v, mut u_size, mut v_size] = uv;
+        if options.flip_x {
+            u += u_size;
+            u_size = -u_size;
+        }
+        if options.flip_y {
+            v += v_size;
+            v_size = -v_size;
+        }
+        
+        let index = self.instances.len() as u32;
+        self.instances.push(SpriteInstance {
+            position: [rect.x, rect.y],
+            size: [rect.width, rect.height],
+            uv_offset: [u, v],
+            uv_size: [u_size, v_size],
+            tint: options.tint,
+        });
+        
+        // Extend the previous run when it uses the same page and space, otherwise start a new one
+        match self.commands.last_mut() {
+            Some(DrawCommand::Sprites(batch)) if batch.page == page && batch.space == self.space => {
+                batch.instances.end = index + 1
+            },
+            _ => self.commands.push(DrawCommand::Sprites(SpriteBatch {
+                page,
+                space: self.space,
+                instances: index..index + 1,
+            })),
+        }
+    }
+    
+    // Upload the queued sprites and draw them all in a single render pass.
+    // Pass a clear color to clear the target first, or None to draw on top of it.
+    pub fn flush(&mut self, 
+                 device: &Device, 
+                 queue: &Queue, 
+                 view: &TextureView, 
+                 clear_color: Option<wgpu::Color>) {
+        // Grow the instance buffer if this frame has more sprites than it can hold
+        if self.instances.len() > self.instance_capacity {
+            self.instance_capacity = self.instances.len().next_power_of_two();
+            self.instance_buffer = Self::create_instance_buffer(device, self.instance_capacity);
+        }
+        
+        if !self.instances.is_empty() {
+            queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&self.instances));
+        }
+        
+        let load = match clear_color {
+            Some(color) => wgpu::LoadOp::Clear(color),
+            None => wgpu::LoadOp::Load,
+        };
+        
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Sprite Batch Encoder"),
+        });
+        
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Sprite Batch Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            
+            render_pass.set_pipeline(&self.render_pipeline);
+            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            
+            // One draw call per page run. Static batches bring their own instance
+            // buffer, so rebind the frame's buffer when switching back to it.
+            for command in &self.commands {
+                match command {
+                    DrawCommand::Sprites(batch) => {
+                        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+                        self.draw_run(&mut render_pass, batch);
+                    },
+                    DrawCommand::Static(id) => {
+                        let Some(static_batch) = self.static_batches.get(id) else { continue };
+                        let Some(buffer) = &static_batch.buffer else { continue };
+                        render_pass.set_vertex_buffer(1, buffer.slice(..));
+                        for run in &static_batch.runs {
+                            self.draw_run(&mut render_pass, run);
+                        }
+                    },
+                }
+            }
+        }
+        
+        queue.submit(std::iter::once(encoder.finish()));
+    }    
+    
+    fn draw_run<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, batch: &SpriteBatch) {
+        let camera_bind_group = match batch.space {
+            Space::World => &self.camera_bind_group,
+            Space::Screen => &self.screen_bind_group,
+        };
+        render_pass.set_bind_group(0, &self.page_bind_groups[batch.page], &[]);
+        render_pass.set_bind_group(1, camera_bind_group, &[]);
+        render_pass.draw_indexed(0..6, 0, batch.instances.clone());
+    }
+    
+    // Draw the current batch into an offscreen texture and read it back.
+    // Works with a headless device, and the batch is left intact so it can
+    // also be flushed to the window in the same frame.
+    pub fn render_to_image(&mut self, 
+                           device: &Device, 
+                           queue: &Queue, 
+                           width: u32, 
+                           height: u32, 
+                           clear_color: Option<wgpu::Color>) -> Result<image::RgbaImage, String> {
+        let target = OffscreenTarget::new(device, width, height, self.format);
+        self.flush(device, queue, &target.view, clear_color);
+        target.read_pixels(device, queue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::gpu::GpuContext;
+
+    const BACKGROUND: wgpu::Color = wgpu::Color { r: 0.0, g: 0.0, b: 1.0, a: 1.0 };
+
+    #[test]
+    fn renders_a_rect_offscreen_and_reads_it_back() {
+        // CI machines without any adapter, not even a software one, can't run this
+        let gpu = match GpuContext::headless() {
+            Ok(gpu) => gpu,
+            Err(error) => {
+                eprintln!("Skipping offscreen render test: {}", error);
+                return;
+            },
+        };
+
+        let mut renderer = Renderer::new(&gpu.device, &gpu.queue, wgpu::TextureFormat::Rgba8Unorm);
+        renderer.set_camera(&gpu.queue, &Camera2D::new(64.0, 64.0));
+        renderer.begin_batch();
+        renderer.set_space(Space::Screen);
+        // Red over the top-left quarter, so a flipped image would show up too
+        renderer.push_rect(0.0, 0.0, 32.0, 32.0, [1.0, 0.0, 0.0, 1.0]);
+
+        let image = renderer.render_to_image(&gpu.device, &gpu.queue, 64, 64, Some(BACKGROUND))
+            .expect("Failed to render offscreen");
+        assert_eq!(image.dimensions(), (64, 64));
+        assert_eq!(image.get_pixel(8, 8).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(56, 56).0, [0, 0, 255, 255]);
+        assert_eq!(image.get_pixel(56, 8).0, [0, 0, 255, 255]);
+    }
+}
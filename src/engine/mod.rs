@@ -1,2 +1,9 @@
-pub mod state;
-pub mod graphics;
\ No newline at end of file
+pub mod state;
+pub mod graphics;
+pub mod camera;
+pub mod animation;
+pub mod atlas;
+pub mod text;
+pub mod gpu;
+pub mod physics;
+pub mod watch;
\ No newline at end of file
@@ -0,0 +1,408 @@
+use nalgebra as na;
+
+// Anything made of square tiles that boxes can bump into
+pub trait TileGrid {
+    // Width and height of one tile, in world pixels
+    fn tile_size(&self) -> f32;
+
+    // Whether the tile at (x, y) blocks movement. Coordinates can be negative
+    // or past the far edge, so grids decide for themselves what's out there.
+    fn is_solid_at(&self, x: i32, y: i32) -> bool;
+
+    // Whether the tile at (x, y) only stops boxes landing on it from above
+    fn is_one_way_at(&self, _x: i32, _y: i32) -> bool {
+        false
+    }
+}
+
+// How far a box's bottom can already be into a one-way tile and still land on it
+const ONE_WAY_TOLERANCE: f32 = 1.0;
+
+// How a box moves through tiles that are only sometimes in the way
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MoveOptions {
+    // Fall through one-way tiles instead of landing on them
+    pub drop_through: bool,
+}
+
+// Where a box ended up after moving, and what it ran into on the way
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Collision {
+    // Centre of the box
+    pub position: na::Vector2<f32>,
+    // Normal of the surface hit on each axis, pointing back out of it, so
+    // landing on a floor gives (0, -1). Zero on an axis where nothing was hit.
+    pub normal: na::Vector2<f32>,
+}
+
+impl Collision {
+    pub fn on_ground(&self) -> bool {
+        self.normal.y < 0.0
+    }
+
+    pub fn hit_ceiling(&self) -> bool {
+        self.normal.y > 0.0
+    }
+
+    pub fn hit_wall(&self) -> bool {
+        self.normal.x != 0.0
+    }
+}
+
+// Move a box of `size` centred on `position` by `delta`, stopping it against
+// solid tiles. X moves first and is resolved before Y, so sliding along floors
+// and walls works and corners can't be cut. Every tile between the start and
+// the end is checked, so nothing is fast enough to pass through a wall.
+pub fn move_and_collide<G: TileGrid + ?Sized>(grid: &G,
+                                              position: na::Vector2<f32>,
+                                              size: na::Vector2<f32>,
+                                              delta: na::Vector2<f32>) -> Collision {
+    move_and_collide_with(grid, position, size, delta, MoveOptions::default())
+}
+
+// move_and_collide, with a say in how one-way tiles are treated
+pub fn move_and_collide_with<G: TileGrid + ?Sized>(grid: &G,
+                                                   position: na::Vector2<f32>,
+                                                   size: na::Vector2<f32>,
+                                                   delta: na::Vector2<f32>,
+                                                   options: MoveOptions) -> Collision {
+    let half = size / 2.0;
+    let tile_size = grid.tile_size();
+    let mut position = position;
+    let mut normal = na::Vector2::zeros();
+
+    if delta.x != 0.0 {
+        let (x, normal_x) = sweep(delta.x, position.x, half.x, position.y, half.y, tile_size,
+                                  |column, row| grid.is_solid_at(column, row));
+        position.x = x;
+        normal.x = normal_x;
+    }
+
+    if delta.y != 0.0 {
+        // Same again with the axes swapped, from wherever X ended up. One-way
+        // tiles only stop boxes coming down that started out above them.
+        let bottom = position.y + half.y;
+        let landing = delta.y > 0.0 && !options.drop_through;
+        let (y, normal_y) = sweep(delta.y, position.y, half.y, position.x, half.x, tile_size,
+                                  |row, column| {
+            grid.is_solid_at(column, row) ||
+                (landing && grid.is_one_way_at(column, row) && bottom <= row as f32 * tile_size + ONE_WAY_TOLERANCE)
+        });
+        position.y = y;
+        normal.y = normal_y;
+    }
+
+    Collision { position, normal }
+}
+
+// Move along one axis. `along` is the box's centre on that axis and `across`
+// its centre on the other one; `is_solid` takes (along, across) tile coordinates.
+// Returns the new centre and the normal of whatever was hit, or 0.
+fn sweep(delta: f32,
+         along: f32,
+         half_along: f32,
+         across: f32,
+         half_across: f32,
+         tile_size: f32,
+         is_solid: impl Fn(i32, i32) -> bool) -> (f32, f32) {
+    // The tiles the box covers across the direction of travel. A box
+    // edge lying exactly on a tile boundary only touches the next tile,
+    // it doesn't overlap it, so standing on a floor doesn't count as
+    // running into it.
+    let first_across = ((across - half_across) / tile_size).floor() as i32;
+    let last_across = ((across + half_across) / tile_size).ceil() as i32 - 1;
+
+    if delta > 0.0 {
+        // Check every tile the leading edge passes through, nearest first
+        let edge = along + half_along;
+        let first = (edge / tile_size).floor() as i32;
+        let last = ((edge + delta) / tile_size).ceil() as i32 - 1;
+        for tile in first..=last {
+            if (first_across..=last_across).any(|other| is_solid(tile, other)) {
+                let stop = tile as f32 * tile_size - half_along;
+                // Never pushed backwards by a tile we already overlap
+                return (stop.max(along), -1.0);
+            }
+        }
+    } else {
+        let edge = along - half_along;
+        let first = (edge / tile_size).ceil() as i32 - 1;
+        let last = ((edge + delta) / tile_size).floor() as i32;
+        for tile in (last..=first).rev() {
+            if (first_across..=last_across).any(|other| is_solid(tile, other)) {
+                let stop = (tile + 1) as f32 * tile_size + half_along;
+                return (stop.min(along), 1.0);
+            }
+        }
+    }
+
+    (along + delta, 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TILE: f32 = 32.0;
+
+    // '#' is solid, '-' is one-way, anything else is empty, and everything outside is solid
+    struct Grid(Vec<Vec<char>>);
+
+    impl Grid {
+        fn new(rows: &[&str]) -> Self {
+            Grid(rows.iter().map(|row| row.chars().collect()).collect())
+        }
+    }
+
+    impl TileGrid for Grid {
+        fn tile_size(&self) -> f32 {
+            TILE
+        }
+
+        fn is_solid_at(&self, x: i32, y: i32) -> bool {
+            if x < 0 || y < 0 {
+                return true;
+            }
+            self.0.get(y as usize)
+                .and_then(|row| row.get(x as usize))
+                .is_none_or(|&c| c == '#')
+        }
+
+        fn is_one_way_at(&self, x: i32, y: i32) -> bool {
+            x >= 0 && y >= 0 && self.0.get(y as usize).and_then(|row| row.get(x as usize)) == Some(&'-')
+        }
+    }
+
+    fn v(x: f32, y: f32) -> na::Vector2<f32> {
+        na::Vector2::new(x, y)
+    }
+
+    // The player's box
+    fn size() -> na::Vector2<f32> {
+        v(24.0, 48.0)
+    }
+
+    #[test]
+    fn moves_freely_through_empty_space() {
+        let grid = Grid::new(&[
+            "......",
+            "......",
+            "......",
+            "......",
+        ]);
+        let collision = move_and_collide(&grid, v(64.0, 48.0), size(), v(10.0, 5.0));
+        assert_eq!(collision.position, v(74.0, 53.0));
+        assert_eq!(collision.normal, v(0.0, 0.0));
+    }
+
+    #[test]
+    fn lands_on_the_floor() {
+        let grid = Grid::new(&[
+            "......",
+            "......",
+            "......",
+            "######",
+        ]);
+        // Bottom edge at 90, floor top at 96
+        let collision = move_and_collide(&grid, v(64.0, 66.0), size(), v(0.0, 20.0));
+        assert_eq!(collision.position, v(64.0, 72.0));
+        assert!(collision.on_ground());
+        assert_eq!(collision.normal, v(0.0, -1.0));
+    }
+
+    #[test]
+    fn slides_along_the_floor() {
+        let grid = Grid::new(&[
+            "......",
+            "......",
+            "......",
+            "######",
+        ]);
+        // Standing exactly on the floor shouldn't stop sideways movement
+        let collision = move_and_collide(&grid, v(64.0, 72.0), size(), v(12.0, 0.0));
+        assert_eq!(collision.position, v(76.0, 72.0));
+        assert!(!collision.hit_wall());
+    }
+
+    #[test]
+    fn stops_at_walls_on_either_side() {
+        let grid = Grid::new(&[
+            "#....#",
+            "#....#",
+            "#....#",
+            "######",
+        ]);
+        let right = move_and_collide(&grid, v(140.0, 72.0), size(), v(20.0, 0.0));
+        assert_eq!(right.position, v(148.0, 72.0));
+        assert_eq!(right.normal, v(-1.0, 0.0));
+
+        let left = move_and_collide(&grid, v(50.0, 72.0), size(), v(-20.0, 0.0));
+        assert_eq!(left.position, v(44.0, 72.0));
+        assert_eq!(left.normal, v(1.0, 0.0));
+    }
+
+    #[test]
+    fn bumps_into_the_ceiling() {
+        let grid = Grid::new(&[
+            "######",
+            "......",
+            "......",
+            "......",
+        ]);
+        // Top edge at 40, ceiling bottom at 32
+        let collision = move_and_collide(&grid, v(64.0, 64.0), size(), v(0.0, -20.0));
+        assert_eq!(collision.position, v(64.0, 56.0));
+        assert!(collision.hit_ceiling());
+        assert_eq!(collision.normal, v(0.0, 1.0));
+    }
+
+    #[test]
+    fn inside_corner_stops_both_axes() {
+        let grid = Grid::new(&[
+            ".....#",
+            ".....#",
+            ".....#",
+            "######",
+        ]);
+        let collision = move_and_collide(&grid, v(140.0, 66.0), size(), v(20.0, 20.0));
+        assert_eq!(collision.position, v(148.0, 72.0));
+        assert_eq!(collision.normal, v(-1.0, -1.0));
+    }
+
+    #[test]
+    fn lands_on_an_outside_corner_instead_of_cutting_it() {
+        let grid = Grid::new(&[
+            "......",
+            "......",
+            "......",
+            "...#..",
+            "......",
+        ]);
+        // Above and to the left of the block. Moving right first clears the
+        // block's column, then falling lands on it rather than slipping past.
+        let collision = move_and_collide(&grid, v(84.0, 66.0), size(), v(10.0, 20.0));
+        assert_eq!(collision.position, v(94.0, 72.0));
+        assert_eq!(collision.normal, v(0.0, -1.0));
+    }
+
+    #[test]
+    fn misses_a_corner_it_only_touches() {
+        let grid = Grid::new(&[
+            "......",
+            "......",
+            "......",
+            "...#..",
+            "......",
+        ]);
+        // Right edge exactly on the block's left edge, falling past it
+        let collision = move_and_collide(&grid, v(84.0, 66.0), size(), v(0.0, 40.0));
+        assert_eq!(collision.position, v(84.0, 106.0));
+        assert_eq!(collision.normal, v(0.0, 0.0));
+    }
+
+    #[test]
+    fn fast_movement_cant_tunnel_through_thin_walls() {
+        let grid = Grid::new(&[
+            "..............................",
+            "..............................",
+            "..........#...................",
+            "..........#...................",
+            "..............................",
+            "..............................",
+            "..............................",
+            "..............................",
+            "..............................",
+            "..............................",
+            "..............................",
+            "##############################",
+        ]);
+        // Several hundred pixels in a single step, far more than a tile
+        let sideways = move_and_collide(&grid, v(48.0, 96.0), size(), v(600.0, 0.0));
+        assert_eq!(sideways.position, v(308.0, 96.0));
+        assert!(sideways.hit_wall());
+
+        let falling = move_and_collide(&grid, v(48.0, 24.0), size(), v(0.0, 5000.0));
+        assert_eq!(falling.position, v(48.0, 328.0));
+        assert!(falling.on_ground());
+    }
+
+    #[test]
+    fn edges_of_the_grid_are_solid_even_at_negative_coordinates() {
+        let grid = Grid::new(&[
+            "......",
+            "......",
+            "......",
+        ]);
+        let collision = move_and_collide(&grid, v(20.0, 40.0), size(), v(-100.0, -100.0));
+        assert_eq!(collision.position, v(12.0, 24.0));
+        assert_eq!(collision.normal, v(1.0, 1.0));
+    }
+
+    #[test]
+    fn lands_on_one_way_platforms_from_above() {
+        let grid = Grid::new(&[
+            "......",
+            "......",
+            "......",
+            "------",
+            "......",
+        ]);
+        let collision = move_and_collide(&grid, v(64.0, 66.0), size(), v(0.0, 20.0));
+        assert_eq!(collision.position, v(64.0, 72.0));
+        assert!(collision.on_ground());
+    }
+
+    #[test]
+    fn jumps_up_through_one_way_platforms() {
+        let grid = Grid::new(&[
+            "......",
+            "......",
+            "------",
+            "......",
+            "......",
+        ]);
+        // From below the platform to standing height above it
+        let collision = move_and_collide(&grid, v(64.0, 130.0), size(), v(0.0, -90.0));
+        assert_eq!(collision.position, v(64.0, 40.0));
+        assert_eq!(collision.normal, v(0.0, 0.0));
+
+        // Falling again after only getting partway through doesn't snap up onto it
+        let collision = move_and_collide(&grid, v(64.0, 60.0), size(), v(0.0, 10.0));
+        assert_eq!(collision.position, v(64.0, 70.0));
+        assert!(!collision.on_ground());
+    }
+
+    #[test]
+    fn walks_through_one_way_platforms_sideways() {
+        let grid = Grid::new(&[
+            "......",
+            "..-...",
+            "..-...",
+            "######",
+        ]);
+        let collision = move_and_collide(&grid, v(40.0, 72.0), size(), v(60.0, 0.0));
+        assert_eq!(collision.position, v(100.0, 72.0));
+        assert!(!collision.hit_wall());
+    }
+
+    #[test]
+    fn drops_through_one_way_platforms_when_asked() {
+        let grid = Grid::new(&[
+            "......",
+            "......",
+            "------",
+            "......",
+            "......",
+            "######",
+        ]);
+        let options = MoveOptions { drop_through: true };
+        let collision = move_and_collide_with(&grid, v(64.0, 40.0), size(), v(0.0, 20.0), options);
+        assert_eq!(collision.position, v(64.0, 60.0));
+        assert!(!collision.on_ground());
+
+        // Solid floors still stop it
+        let collision = move_and_collide_with(&grid, v(64.0, 60.0), size(), v(0.0, 200.0), options);
+        assert_eq!(collision.position, v(64.0, 136.0));
+        assert!(collision.on_ground());
+    }
+}
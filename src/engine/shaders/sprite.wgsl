@@ -1,33 +1,50 @@
-// Vertex shader
-struct VertexInput {
-    @location(0) position: vec3<f32>,
-    @location(1) tex_coords: vec2<f32>,
-};
-
-struct VertexOutput {
-    @builtin(position) clip_position: vec4<f32>,
-    @location(0) tex_coords: vec2<f32>,
-};
-
-// Model matrix as a uniform
-@group(1) @binding(0)
-var<uniform> model: mat4x4<f32>;
-
-@vertex
-fn vs_main(in: VertexInput) -> VertexOutput {
-    var out: VertexOutput;
-    out.clip_position = model * vec4<f32>(in.position, 1.0);
-    out.tex_coords = in.tex_coords;
-    return out;
-}
-
-// Fragment shader
-@group(0) @binding(0)
-var t_diffuse: texture_2d<f32>;
-@group(0) @binding(1)
-var s_diffuse: sampler;
-
-@fragment
-fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(t_diffuse, s_diffuse, in.tex_coords);
-}
\ No newline at end of file
+// Vertex shader
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+};
+
+// Per-sprite data from the instance buffer
+struct InstanceInput {
+    @location(2) position: vec2<f32>,
+    @location(3) size: vec2<f32>,
+    @location(4) uv_offset: vec2<f32>,
+    @location(5) uv_size: vec2<f32>,
+    @location(6) tint: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) tint: vec4<f32>,
+};
+
+// Maps world pixels to clip space
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+};
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
+@vertex
+fn vs_main(in: VertexInput, instance: InstanceInput) -> VertexOutput {
+    var out: VertexOutput;
+    // Scale the unit quad to the sprite size, then move it into place
+    let world = in.position.xy * instance.size + instance.position;
+    out.clip_position = camera.view_proj * vec4<f32>(world, 0.0, 1.0);
+    // Pick out this sprite's frame from the sheet
+    out.tex_coords = instance.uv_offset + in.tex_coords * instance.uv_size;
+    out.tint = instance.tint;
+    return out;
+}
+
+// Fragment shader
+@group(0) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(0) @binding(1)
+var s_diffuse: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(t_diffuse, s_diffuse, in.tex_coords) * in.tint;
+}
@@ -1,119 +1,260 @@
-use winit::event::WindowEvent;
-use winit::window::Window;
-use wgpu::{Device, Queue, Surface, SurfaceConfiguration};
-
-// GameState trait defines what all game states must implement
-pub trait GameState {
-    // Process window events like mouse moves, key presses, etc.
-    fn handle_event(&mut self, event: &WindowEvent) -> bool;
-    
-    // Update game logic
-    fn update(&mut self, dt: f32);
-    
-    // Render the current state
-    fn render(&mut self, device: &Device, queue: &Queue, surface: &Surface, 
-              config: &SurfaceConfiguration) -> Result<(), wgpu::SurfaceError>;
-}
-
-// StateManager holds our graphics resources and the current game state
-pub struct StateManager {
-    pub window: Window,
-    pub surface: Surface,
-    pub device: Device,
-    pub queue: Queue,
-    pub config: SurfaceConfiguration,
-    pub size: winit::dpi::PhysicalSize<u32>,
-    current_state: Box<dyn GameState>,
-}
-
-impl StateManager {
-    // Create a new state manager with the given window and initial state
-    pub fn new(window: Window, device: Device, queue: Queue, initial_state: Box<dyn GameState>) -> Self {
-        let size = window.inner_size();
-        
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(),
-            dx12_shader_compiler: Default::default(),
-        });
-        
-        let surface = unsafe { instance.create_surface(&window) }
-            .expect("Failed to create surface");
-        
-        let adapter = futures::executor::block_on(instance.request_adapter(
-            &wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            },
-        )).expect("Failed to find an appropriate adapter");
-        
-        let surface_caps = surface.get_capabilities(&adapter);
-        let surface_format = surface_caps.formats.iter()
-            .copied()
-            .find(|f| f.is_srgb())
-            .unwrap_or(surface_caps.formats[0]);
-        
-        let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface_format,
-            width: size.width,
-            height: size.height,
-            present_mode: surface_caps.present_modes[0],
-            alpha_mode: surface_caps.alpha_modes[0],
-            view_formats: vec![],
-        };
-        
-        surface.configure(&device, &config);
-        
-        Self {
-            window,
-            surface,
-            device,
-            queue,
-            config,
-            size,
-            current_state: initial_state,
-        }
-    }
-    
-    // Handle window events and pass them to the current state
-    pub fn handle_window_event(&mut self, event: &WindowEvent) -> bool {
-        match event {
-            WindowEvent::Resized(physical_size) => {
-                self.resize(*physical_size);
-                false
-            },
-            WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                self.resize(**new_inner_size);
-                false
-            },
-            // Let the current state handle other events
-            _ => self.current_state.handle_event(event),
-        }
-    }
-    
-    // Handle window resize
-    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
-        if new_size.width > 0 && new_size.height > 0 {
-            self.size = new_size;
-            self.config.width = new_size.width;
-            self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
-        }
-    }
-    
-    // Update the current state
-    pub fn update(&mut self, dt: f32) {
-        self.current_state.update(dt);
-    }
-    
-    // Render the current state
-    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        self.current_state.render(&self.device, &self.queue, &self.surface, &self.config)
-    }
-    
-    // Switch to a new state
-    pub fn change_state(&mut self, new_state: Box<dyn GameState>) {
-        self.current_state = new_state;
-    }
-}
\ No newline at end of file
+use winit::event::WindowEvent;
+use winit::window::Window;
+use wgpu::{Device, Queue, SurfaceConfiguration, TextureView};
+use super::gpu::GpuContext;
+
+// The simulation always advances in steps of this many seconds (120 Hz)
+pub const FIXED_TIMESTEP: f32 = 1.0 / 120.0;
+
+// Most steps run in one frame. After a long stall we drop the backlog rather
+// than spending the next frames catching up, which would stall them too.
+const MAX_FIXED_STEPS: u32 = 8;
+
+// Builds a state once the manager can hand it the GPU, the same way as the
+// initial state passed to StateManager::new
+pub type StateBuilder = Box<dyn FnOnce(&Device, &Queue, &SurfaceConfiguration) -> Box<dyn GameState>>;
+
+// What a state wants the manager to do after handling an event or an update
+pub enum Transition {
+    // Stay in the current state
+    None,
+    // Put a new state on top of this one, e.g. a pause menu
+    Push(StateBuilder),
+    // Remove this state and go back to the one underneath
+    Pop,
+    // Swap this state for a new one
+    Replace(StateBuilder),
+    // Throw away the whole stack and start again from a new state,
+    // e.g. quitting to the title screen from a pause menu
+    Reset(StateBuilder),
+    // Exit the game
+    Quit,
+}
+
+impl Transition {
+    pub fn push<F>(build: F) -> Self
+    where
+        F: FnOnce(&Device, &Queue, &SurfaceConfiguration) -> Box<dyn GameState> + 'static,
+    {
+        Transition::Push(Box::new(build))
+    }
+    
+    pub fn replace<F>(build: F) -> Self
+    where
+        F: FnOnce(&Device, &Queue, &SurfaceConfiguration) -> Box<dyn GameState> + 'static,
+    {
+        Transition::Replace(Box::new(build))
+    }
+    
+    pub fn reset<F>(build: F) -> Self
+    where
+        F: FnOnce(&Device, &Queue, &SurfaceConfiguration) -> Box<dyn GameState> + 'static,
+    {
+        Transition::Reset(Box::new(build))
+    }
+}
+
+// GameState trait defines what all game states must implement
+pub trait GameState {
+    // Process window events like mouse moves, key presses, etc.
+    // Only the state on top of the stack receives events.
+    fn handle_event(&mut self, event: &WindowEvent) -> Transition;
+    
+    // Advance the simulation by exactly FIXED_TIMESTEP seconds. Runs zero or more
+    // times per frame. Only the state on top of the stack is updated, so
+    // anything underneath an overlay is frozen, and drawn with an alpha of 1.0.
+    fn fixed_update(&mut self, _dt: f32) -> Transition {
+        Transition::None
+    }
+    
+    // Per-frame logic that doesn't need a fixed step, like UI timers.
+    // `dt` is the real time since the last frame.
+    fn update(&mut self, _dt: f32) -> Transition {
+        Transition::None
+    }
+    
+    // Render the current state into the frame being drawn. `alpha` is how far
+    // we are between the last fixed update and the next one (0.0 to 1.0), for
+    // drawing moving things in between their simulated positions.
+    fn render(&mut self, device: &Device, queue: &Queue, view: &TextureView,
+              config: &SurfaceConfiguration, alpha: f32);
+    
+    // Called whenever the surface is (re)configured, e.g. after a resize.
+    // States that own GPU pipelines should rebuild them if the format changed.
+    fn resize(&mut self, _device: &Device, _config: &SurfaceConfiguration) {}
+    
+    // Overlays only cover part of the screen, so the states below them are
+    // rendered first. They should draw without clearing the frame.
+    fn is_overlay(&self) -> bool {
+        false
+    }
+}
+
+// StateManager holds our graphics resources and a stack of game states
+pub struct StateManager {
+    // Declared before the window so the surface is dropped first
+    pub gpu: GpuContext,
+    pub window: Window,
+    pub config: SurfaceConfiguration,
+    pub size: winit::dpi::PhysicalSize<u32>,
+    // The last state is the active one
+    states: Vec<Box<dyn GameState>>,
+    // Frame time not yet simulated, always less than FIXED_TIMESTEP between frames
+    accumulator: f32,
+}
+
+impl StateManager {
+    // Create a new state manager with the given window. The initial state is built
+    // once the surface is configured, so it can create pipelines for the right format.
+    pub fn new<F>(window: Window, create_initial_state: F) -> Self
+    where
+        F: FnOnce(&Device, &Queue, &SurfaceConfiguration) -> Box<dyn GameState>,
+    {
+        let size = window.inner_size();
+        
+        let gpu = GpuContext::new(&window)
+            .expect("Failed to initialise graphics");
+        
+        let info = gpu.adapter_info();
+        println!("Using {} ({:?}, {:?})", info.name, info.backend, info.device_type);
+        if gpu.is_software() {
+            println!("No hardware adapter found, rendering in software");
+        }
+        
+        let config = gpu.surface_config(size.width, size.height)
+            .expect("Window context has no surface");
+        gpu.surface().configure(&gpu.device, &config);
+        
+        let initial_state = create_initial_state(&gpu.device, &gpu.queue, &config);
+        
+        Self {
+            gpu,
+            window,
+            config,
+            size,
+            states: vec![initial_state],
+            accumulator: 0.0,
+        }
+    }
+    
+    // Handle window events and pass them to the active state.
+    // Returns true when the game should exit.
+    pub fn handle_window_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CloseRequested => {
+                self.states.clear();
+            },
+            WindowEvent::Resized(physical_size) => {
+                self.resize(*physical_size);
+            },
+            WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                self.resize(**new_inner_size);
+            },
+            // Let the active state handle other events
+            _ => {
+                if let Some(state) = self.states.last_mut() {
+                    let transition = state.handle_event(event);
+                    self.apply_transition(transition);
+                }
+            },
+        }
+        
+        self.states.is_empty()
+    }
+    
+    // Handle window resize
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        if new_size.width > 0 && new_size.height > 0 {
+            self.size = new_size;
+            self.config.width = new_size.width;
+            self.config.height = new_size.height;
+            self.gpu.surface().configure(&self.gpu.device, &self.config);
+            
+            // States under an overlay still get drawn, so they need to know too
+            for state in &mut self.states {
+                state.resize(&self.gpu.device, &self.config);
+            }
+        }
+    }
+    
+    // Run as many fixed steps as the elapsed time covers, then the per-frame
+    // update. Returns true when the game should exit.
+    pub fn update(&mut self, dt: f32) -> bool {
+        self.accumulator += dt;
+        
+        let mut steps = 0;
+        while self.accumulator >= FIXED_TIMESTEP {
+            if steps == MAX_FIXED_STEPS {
+                self.accumulator = 0.0;
+                break;
+            }
+            
+            let Some(state) = self.states.last_mut() else { break };
+            let transition = state.fixed_update(FIXED_TIMESTEP);
+            self.apply_transition(transition);
+            
+            self.accumulator -= FIXED_TIMESTEP;
+            steps += 1;
+        }
+        
+        if let Some(state) = self.states.last_mut() {
+            let transition = state.update(dt);
+            self.apply_transition(transition);
+        }
+        
+        self.states.is_empty()
+    }
+    
+    // Render the stack into the next frame, starting from the topmost state
+    // that covers the whole screen so overlays are drawn over what's below them
+    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        let frame = self.gpu.surface().get_current_texture()?;
+        let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        
+        let alpha = self.accumulator / FIXED_TIMESTEP;
+        let first = self.states.iter().rposition(|state| !state.is_overlay()).unwrap_or(0);
+        let top = self.states.len().saturating_sub(1);
+        for (index, state) in self.states.iter_mut().enumerate().skip(first) {
+            // The accumulator keeps running for the state on top, but the ones
+            // underneath aren't being updated. Draw them where their last step
+            // left them, or they'd jitter back and forth under the overlay.
+            let alpha = if index == top { alpha } else { 1.0 };
+            state.render(&self.gpu.device, &self.gpu.queue, &view, &self.config, alpha);
+        }
+        
+        frame.present();
+        Ok(())
+    }
+    
+    fn apply_transition(&mut self, transition: Transition) {
+        match transition {
+            Transition::None => {},
+            Transition::Push(build) => {
+                let state = self.build_state(build);
+                self.states.push(state);
+            },
+            Transition::Pop => {
+                self.states.pop();
+            },
+            Transition::Replace(build) => {
+                let state = self.build_state(build);
+                self.states.pop();
+                self.states.push(state);
+            },
+            Transition::Reset(build) => {
+                let state = self.build_state(build);
+                self.states.clear();
+                self.states.push(state);
+            },
+            Transition::Quit => {
+                self.states.clear();
+            },
+        }
+    }
+    
+    fn build_state(&self, build: StateBuilder) -> Box<dyn GameState> {
+        let mut state = build(&self.gpu.device, &self.gpu.queue, &self.config);
+        state.resize(&self.gpu.device, &self.config);
+        state
+    }
+}
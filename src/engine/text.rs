@@ -0,0 +1,367 @@
+use std::collections::HashMap;
+use super::atlas::{AtlasBuilder, PackedAtlas};
+
+// Characters rasterized into every glyph atlas (printable ASCII)
+const DEFAULT_CHARSET: std::ops::RangeInclusive<char> = ' '..='~';
+
+// Shown for characters that aren't in the atlas
+const FALLBACK_CHAR: char = '?';
+
+const GLYPH_PAGE_SIZE: u32 = 512;
+
+// Horizontal alignment of each line inside the text box
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+// A run of text drawn in a single colour
+#[derive(Clone, Debug)]
+pub struct TextSpan {
+    pub text: String,
+    pub color: [f32; 4],
+}
+
+impl TextSpan {
+    pub fn new(text: impl Into<String>, color: [f32; 4]) -> Self {
+        Self {
+            text: text.into(),
+            color,
+        }
+    }
+}
+
+// Where and how a block of text is laid out
+#[derive(Clone, Copy, Debug)]
+pub struct TextStyle {
+    // Wrap lines longer than this many pixels. None keeps each line whole.
+    pub max_width: Option<f32>,
+    pub align: TextAlign,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            max_width: None,
+            align: TextAlign::Left,
+        }
+    }
+}
+
+// Size and placement of one rasterized glyph, in pixels
+#[derive(Clone, Copy, Debug)]
+pub struct GlyphMetrics {
+    pub width: f32,
+    pub height: f32,
+    // From the pen position on the baseline to the bitmap's top-left corner (y down)
+    pub offset_x: f32,
+    pub offset_y: f32,
+    // How far the pen moves after this glyph
+    pub advance: f32,
+}
+
+// A font rasterized at one pixel size. Glyph bitmaps are packed into atlas
+// pages as white pixels with coverage in the alpha channel, so spans can be
+// coloured with the sprite tint.
+pub struct GlyphAtlas {
+    pub atlas: PackedAtlas,
+    pub glyphs: HashMap<char, GlyphMetrics>,
+    // Distance from the top of a line to its baseline
+    pub ascent: f32,
+    // Distance between the baselines of consecutive lines
+    pub line_height: f32,
+}
+
+impl GlyphAtlas {
+    // Rasterize printable ASCII from a TrueType/OpenType font at `px` pixels
+    pub fn from_ttf(bytes: &[u8], px: f32) -> Result<Self, String> {
+        let font = fontdue::Font::from_bytes(bytes, fontdue::FontSettings::default())
+            .map_err(|e| format!("Failed to load font: {}", e))?;
+
+        let line_metrics = font.horizontal_line_metrics(px)
+            .ok_or("Font has no horizontal line metrics")?;
+
+        let mut builder = AtlasBuilder::new(GLYPH_PAGE_SIZE);
+        let mut glyphs = HashMap::new();
+
+        for ch in DEFAULT_CHARSET {
+            let (metrics, coverage) = font.rasterize(ch, px);
+
+            // Whitespace has no bitmap but still needs its advance
+            if metrics.width > 0 && metrics.height > 0 {
+                let mut bitmap = image::RgbaImage::new(metrics.width as u32, metrics.height as u32);
+                for (pixel, &alpha) in bitmap.pixels_mut().zip(coverage.iter()) {
+                    *pixel = image::Rgba([255, 255, 255, alpha]);
+                }
+                builder.add_image(&ch.to_string(), bitmap);
+            }
+
+            glyphs.insert(ch, GlyphMetrics {
+                width: metrics.width as f32,
+                height: metrics.height as f32,
+                offset_x: metrics.xmin as f32,
+                // fontdue measures ymin upwards from the baseline to the bottom of the bitmap
+                offset_y: -(metrics.ymin as f32 + metrics.height as f32),
+                advance: metrics.advance_width,
+            });
+        }
+
+        Ok(Self {
+            atlas: builder.build()?,
+            glyphs,
+            ascent: line_metrics.ascent,
+            line_height: line_metrics.new_line_size,
+        })
+    }
+
+    // Metrics for a character, falling back to '?' for anything missing
+    pub fn glyph(&self, ch: char) -> Option<(char, &GlyphMetrics)> {
+        self.glyphs.get(&ch).map(|g| (ch, g))
+            .or_else(|| self.glyphs.get(&FALLBACK_CHAR).map(|g| (FALLBACK_CHAR, g)))
+    }
+}
+
+// A glyph placed by layout_text, relative to the top-left of the text box
+#[derive(Clone, Copy, Debug)]
+pub struct PositionedGlyph {
+    pub ch: char,
+    // Top-left corner of the glyph bitmap
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub color: [f32; 4],
+}
+
+// The result of laying out some text
+pub struct TextLayout {
+    pub glyphs: Vec<PositionedGlyph>,
+    pub width: f32,
+    pub height: f32,
+}
+
+// Lay out coloured spans, wrapping at spaces (or mid-word when a word alone is
+// too long) and aligning each line within the box. Spaces between words are
+// kept as they are; the ones where a line wraps are dropped.
+pub fn layout_text(font: &GlyphAtlas, spans: &[TextSpan], style: &TextStyle) -> TextLayout {
+    let advance = |ch: char| font.glyph(ch).map_or(0.0, |(_, g)| g.advance);
+
+    // Break the text into lines of (char, colour), without trailing spaces
+    let mut lines: Vec<Vec<(char, [f32; 4])>> = vec![Vec::new()];
+    let mut line_width = 0.0;
+    let mut word: Vec<(char, [f32; 4])> = Vec::new();
+    let mut word_width = 0.0;
+    // The colours of the spaces since the last word, which go in front of the next one
+    let mut gap: Vec<[f32; 4]> = Vec::new();
+
+    // Moves the pending spaces and word onto the current line, wrapping first
+    // if they don't fit
+    let commit_word = |lines: &mut Vec<Vec<(char, [f32; 4])>>,
+                       line_width: &mut f32,
+                       gap: &mut Vec<[f32; 4]>,
+                       word: &mut Vec<(char, [f32; 4])>,
+                       word_width: &mut f32| {
+        if let Some(max_width) = style.max_width {
+            let line = lines.last().unwrap();
+            let space = if line.is_empty() { 0.0 } else { gap.len() as f32 * advance(' ') };
+            if !line.is_empty() && *line_width + space + *word_width > max_width {
+                lines.push(Vec::new());
+                *line_width = 0.0;
+            }
+
+            // A single word wider than the box gets split wherever it overflows
+            if *word_width > max_width {
+                for &(ch, color) in word.iter() {
+                    let width = advance(ch);
+                    if *line_width + width > max_width && !lines.last().unwrap().is_empty() {
+                        lines.push(Vec::new());
+                        *line_width = 0.0;
+                    }
+                    lines.last_mut().unwrap().push((ch, color));
+                    *line_width += width;
+                }
+                gap.clear();
+                word.clear();
+                *word_width = 0.0;
+                return;
+            }
+        }
+
+        let line = lines.last_mut().unwrap();
+        if !line.is_empty() {
+            for &color in gap.iter() {
+                line.push((' ', color));
+                *line_width += advance(' ');
+            }
+        }
+        gap.clear();
+        line.append(word);
+        *line_width += *word_width;
+        *word_width = 0.0;
+    };
+
+    for span in spans {
+        for ch in span.text.chars() {
+            match ch {
+                ' ' => {
+                    if !word.is_empty() {
+                        commit_word(&mut lines, &mut line_width, &mut gap, &mut word, &mut word_width);
+                    }
+                    gap.push(span.color);
+                }
+                '\n' => {
+                    if !word.is_empty() {
+                        commit_word(&mut lines, &mut line_width, &mut gap, &mut word, &mut word_width);
+                    }
+                    gap.clear();
+                    lines.push(Vec::new());
+                    line_width = 0.0;
+                }
+                _ => {
+                    word.push((ch, span.color));
+                    word_width += advance(ch);
+                }
+            }
+        }
+    }
+    if !word.is_empty() {
+        commit_word(&mut lines, &mut line_width, &mut gap, &mut word, &mut word_width);
+    }
+
+    // Measure lines so they can be aligned inside the box
+    let line_widths: Vec<f32> = lines.iter()
+        .map(|line| line.iter().map(|&(ch, _)| advance(ch)).sum())
+        .collect();
+    let widest = line_widths.iter().cloned().fold(0.0, f32::max);
+    let box_width = style.max_width.unwrap_or(widest);
+
+    let mut glyphs = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        let mut pen_x = match style.align {
+            TextAlign::Left => 0.0,
+            TextAlign::Center => (box_width - line_widths[i]) / 2.0,
+            TextAlign::Right => box_width - line_widths[i],
+        };
+        let baseline = i as f32 * font.line_height + font.ascent;
+
+        for &(ch, color) in line {
+            if let Some((ch, metrics)) = font.glyph(ch) {
+                if metrics.width > 0.0 {
+                    glyphs.push(PositionedGlyph {
+                        ch,
+                        x: pen_x + metrics.offset_x,
+                        y: baseline + metrics.offset_y,
+                        width: metrics.width,
+                        height: metrics.height,
+                        color,
+                    });
+                }
+                pen_x += metrics.advance;
+            }
+        }
+    }
+
+    TextLayout {
+        glyphs,
+        width: widest,
+        height: lines.len() as f32 * font.line_height,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+    const WHITE: [f32; 4] = [1.0; 4];
+
+    // A monospaced font where every character is 10 pixels wide, with no
+    // atlas behind it since layout only needs the metrics
+    fn font() -> GlyphAtlas {
+        let glyphs = DEFAULT_CHARSET
+            .map(|ch| (ch, GlyphMetrics {
+                width: if ch == ' ' { 0.0 } else { 8.0 },
+                height: 10.0,
+                offset_x: 0.0,
+                offset_y: -10.0,
+                advance: 10.0,
+            }))
+            .collect();
+        GlyphAtlas {
+            atlas: PackedAtlas { page_size: 0, pages: Vec::new(), regions: HashMap::new() },
+            glyphs,
+            ascent: 10.0,
+            line_height: 12.0,
+        }
+    }
+
+    fn lay_out(spans: &[TextSpan], max_width: Option<f32>, align: TextAlign) -> TextLayout {
+        layout_text(&font(), spans, &TextStyle { max_width, align })
+    }
+
+    // Each line of left-aligned text as a string, spaces included
+    fn rows(layout: &TextLayout) -> Vec<String> {
+        let mut rows: Vec<Vec<char>> = Vec::new();
+        for glyph in &layout.glyphs {
+            let row = (glyph.y / 12.0).round() as usize;
+            let column = (glyph.x / 10.0).round() as usize;
+            if rows.len() <= row {
+                rows.resize(row + 1, Vec::new());
+            }
+            if rows[row].len() <= column {
+                rows[row].resize(column + 1, ' ');
+            }
+            rows[row][column] = glyph.ch;
+        }
+        rows.into_iter().map(|row| row.into_iter().collect()).collect()
+    }
+
+    fn plain(text: &str) -> Vec<TextSpan> {
+        vec![TextSpan::new(text, WHITE)]
+    }
+
+    #[test]
+    fn wraps_at_the_last_space_that_fits() {
+        let layout = lay_out(&plain("one two three"), Some(80.0), TextAlign::Left);
+        assert_eq!(rows(&layout), ["one two", "three"]);
+        assert_eq!(layout.width, 70.0);
+        assert_eq!(layout.height, 24.0);
+    }
+
+    #[test]
+    fn splits_words_too_long_for_a_line() {
+        let layout = lay_out(&plain("ok abcdefghij"), Some(40.0), TextAlign::Left);
+        assert_eq!(rows(&layout), ["ok", "abcd", "efgh", "ij"]);
+    }
+
+    #[test]
+    fn keeps_runs_of_spaces_but_not_where_a_line_wraps() {
+        let layout = lay_out(&plain("a  b"), None, TextAlign::Left);
+        assert_eq!(rows(&layout), ["a  b"]);
+
+        let layout = lay_out(&plain("aaaa   bbbb"), Some(60.0), TextAlign::Left);
+        assert_eq!(rows(&layout), ["aaaa", "bbbb"]);
+    }
+
+    #[test]
+    fn aligns_each_line_inside_the_box() {
+        let glyph_xs = |align| lay_out(&plain("ab\nabcd"), Some(100.0), align).glyphs
+            .iter().map(|glyph| glyph.x).collect::<Vec<_>>();
+        assert_eq!(glyph_xs(TextAlign::Left), [0.0, 10.0, 0.0, 10.0, 20.0, 30.0]);
+        assert_eq!(glyph_xs(TextAlign::Center), [40.0, 50.0, 30.0, 40.0, 50.0, 60.0]);
+        assert_eq!(glyph_xs(TextAlign::Right), [80.0, 90.0, 60.0, 70.0, 80.0, 90.0]);
+    }
+
+    #[test]
+    fn a_colour_change_mid_word_keeps_the_word_together() {
+        let spans = [TextSpan::new("see ev", WHITE), TextSpan::new("idence", RED)];
+        let layout = lay_out(&spans, Some(100.0), TextAlign::Left);
+        assert_eq!(rows(&layout), ["see", "evidence"]);
+
+        let colors: Vec<_> = layout.glyphs.iter().map(|glyph| glyph.color).collect();
+        assert_eq!(colors[3..5], [WHITE, WHITE]);
+        assert!(colors[5..].iter().all(|&color| color == RED));
+    }
+}
@@ -0,0 +1,53 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// Notices when a file changes on disk, by checking its modification time
+// every `interval` seconds. Cheap enough to poll every frame.
+pub struct FileWatcher {
+    path: PathBuf,
+    // When the file was last changed, as of the last check. None if it
+    // couldn't be read then.
+    modified: Option<SystemTime>,
+    interval: f32,
+    timer: f32,
+}
+
+impl FileWatcher {
+    // Start watching `path` as it is now, so only later changes count
+    pub fn new(path: &Path, interval: f32) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            modified: Self::modified_time(path),
+            interval,
+            timer: interval,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Count down `dt` seconds and, if it's time to check, return whether
+    // the file has changed since the last check
+    pub fn poll(&mut self, dt: f32) -> bool {
+        self.timer -= dt;
+        if self.timer > 0.0 {
+            return false;
+        }
+        self.timer = self.interval;
+
+        let modified = Self::modified_time(&self.path);
+        if modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+        // Some editors delete the file before writing the new one. Wait
+        // until it's back rather than reporting a change we can't read.
+        modified.is_some()
+    }
+
+    fn modified_time(path: &Path) -> Option<SystemTime> {
+        fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+    }
+}
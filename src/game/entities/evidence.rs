@@ -0,0 +1,40 @@
+// src/game/entities/evidence.rs
+use crate::ecs::components::{Collider, CollisionType, EvidenceItem, Position, PreviousPosition, Sprite, EVIDENCE_LAYER};
+use crate::game::level::{Evidence, TILE_SIZE};
+use legion::Entity;
+use nalgebra as na;
+
+// Collected evidence stays behind as a faint ghost
+pub const EVIDENCE_GHOST_TINT: [f32; 4] = [1.0, 1.0, 1.0, 0.25];
+
+// Add a piece of evidence to the ECS world, filling the tile it was placed on.
+// Evidence the player already has is spawned as a ghost that can't be picked up again.
+pub fn spawn_evidence(world: &mut legion::World, evidence: &Evidence, collected: bool) -> Entity {
+    let position = na::Vector2::new(
+        (evidence.x as f32 + 0.5) * TILE_SIZE,
+        (evidence.y as f32 + 0.5) * TILE_SIZE,
+    );
+    let tint = if collected { EVIDENCE_GHOST_TINT } else { [1.0, 1.0, 1.0, 1.0] };
+    world.push((
+        Position(position),
+        PreviousPosition(position),
+        Sprite {
+            name: Some("evidence"),
+            width: TILE_SIZE,
+            height: TILE_SIZE,
+            tint,
+            flip_x: false,
+            layer: EVIDENCE_LAYER,
+        },
+        Collider {
+            width: TILE_SIZE,
+            height: TILE_SIZE,
+            collision_type: CollisionType::Evidence,
+        },
+        EvidenceItem {
+            id: evidence.id.clone(),
+            points: evidence.points,
+            collected,
+        },
+    ))
+}
@@ -1 +1,3 @@
+pub mod evidence;
+pub mod npc;
 pub mod player;
\ No newline at end of file
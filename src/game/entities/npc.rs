@@ -0,0 +1,35 @@
+// src/game/entities/npc.rs
+use crate::ecs::components::{Collider, CollisionType, Position, PreviousPosition, Sprite, Talker, NPC_LAYER};
+use crate::game::level::Npc;
+use legion::Entity;
+use nalgebra as na;
+
+// Stand-in for NPC sprites until we have some
+pub const NPC_COLOR: [f32; 4] = [0.4, 0.6, 1.0, 1.0];
+
+// Add an NPC to the ECS world. Levels give the top-left corner of the NPC's
+// box, entities are positioned by their centre.
+pub fn spawn_npc(world: &mut legion::World, npc: &Npc) -> Entity {
+    let position = na::Vector2::new(npc.x + npc.width / 2.0, npc.y + npc.height / 2.0);
+    world.push((
+        Position(position),
+        PreviousPosition(position),
+        Sprite {
+            name: None,
+            width: npc.width,
+            height: npc.height,
+            tint: NPC_COLOR,
+            flip_x: false,
+            layer: NPC_LAYER,
+        },
+        Collider {
+            width: npc.width,
+            height: npc.height,
+            collision_type: CollisionType::Trigger,
+        },
+        Talker {
+            name: npc.name.clone(),
+            dialogue: npc.dialogue.clone(),
+        },
+    ))
+}
@@ -1,137 +1,645 @@
-use std::collections::HashMap;
-
-// Define different tile types
-pub enum TileType {
-    Empty,
-    Platform,
-    Wall,
-    Evidence,
-}
-
-// A simple 2D tile-based level
-pub struct Level {
-    pub width: usize,
-    pub height: usize,
-    pub tiles: Vec<TileType>,
-    pub perspective: Perspective,
-    pub spawn_point: (f32, f32),
-    pub evidence_locations: Vec<(usize, usize)>,
-}
-
-// The perspective of the level
-pub enum Perspective {
-    SideScrolling,
-    TopDown,
-}
-
-impl Level {
-    // Create a new empty level
-    pub fn new(width: usize, height: usize, perspective: Perspective) -> Self {
-        let tiles = vec![TileType::Empty; width * height];
-        Self {
-            width,
-            height,
-            tiles,
-            perspective,
-            spawn_point: (0.0, 0.0),
-            evidence_locations: Vec::new(),
-        }
-    }
-    
-    // Get a tile at a specific position
-    pub fn get_tile(&self, x: usize, y: usize) -> Option<&TileType> {
-        if x < self.width && y < self.height {
-            Some(&self.tiles[y * self.width + x])
-        } else {
-            None
-        }
-    }
-    
-    // Set a tile at a specific position
-    pub fn set_tile(&mut self, x: usize, y: usize, tile_type: TileType) {
-        if x < self.width && y < self.height {
-            self.tiles[y * self.width + x] = tile_type;
-        }
-    }
-    
-    // Set the spawn point
-    pub fn set_spawn_point(&mut self, x: f32, y: f32) {
-        self.spawn_point = (x, y);
-    }
-    
-    // Add an evidence location
-    pub fn add_evidence(&mut self, x: usize, y: usize) {
-        self.evidence_locations.push((x, y));
-        // Also update the tile to be evidence
-        self.set_tile(x, y, TileType::Evidence);
-    }
-    
-    // Load a level from a string representation
-    pub fn from_string(data: &str, perspective: Perspective) -> Self {
-        let lines: Vec<&str> = data.trim().lines().collect();
-        let height = lines.len();
-        let width = lines[0].len();
-        
-        let mut level = Self::new(width, height, perspective);
-        
-        for (y, line) in lines.iter().enumerate() {
-            for (x, c) in line.chars().enumerate() {
-                match c {
-                    '#' => level.set_tile(x, y, TileType::Platform),
-                    'W' => level.set_tile(x, y, TileType::Wall),
-                    'E' => level.add_evidence(x, y),
-                    'S' => {
-                        level.set_spawn_point(x as f32 * 32.0, y as f32 * 32.0); // Assuming 32x32 tiles
-                        level.set_tile(x, y, TileType::Empty);
-                    },
-                    _ => level.set_tile(x, y, TileType::Empty),
-                }
-            }
-        }
-        
-        level
-    }
-}
-
-// A collection of levels
-pub struct World {
-    pub levels: HashMap<String, Level>,
-    pub current_level: String,
-}
-
-impl World {
-    pub fn new() -> Self {
-        Self {
-            levels: HashMap::new(),
-            current_level: String::new(),
-        }
-    }
-    
-    // Add a level to the world
-    pub fn add_level(&mut self, name: &str, level: Level) {
-        self.levels.insert(name.to_string(), level);
-        if self.current_level.is_empty() {
-            self.current_level = name.to_string();
-        }
-    }
-    
-    // Switch to a different level
-    pub fn switch_level(&mut self, name: &str) -> bool {
-        if self.levels.contains_key(name) {
-            self.current_level = name.to_string();
-            true
-        } else {
-            false
-        }
-    }
-    
-    // Get the current level
-    pub fn current_level(&self) -> Option<&Level> {
-        self.levels.get(&self.current_level)
-    }
-    
-    // Get a mutable reference to the current level
-    pub fn current_level_mut(&mut self) -> Option<&mut Level> {
-        self.levels.get_mut(&self.current_level)
-    }
-}
\ No newline at end of file
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use crate::levels::loader::{self, rect_contains, CampaignData, ExitZone, LevelData, LevelMovement, PerspectiveZone, Platform};
+use crate::levels::error::LevelError;
+use crate::levels::tiled;
+use crate::game::entities::player::PLAYER_HEIGHT;
+use crate::engine::physics::TileGrid;
+
+// The same enum the level files use
+pub use crate::levels::loader::Perspective;
+
+// Size of a tile in world pixels
+pub const TILE_SIZE: f32 = 32.0;
+
+// Spikes only fill the bottom of a hazard tile, starting this far down it as
+// a fraction of a tile. Only touching the spikes themselves hurts.
+pub const HAZARD_TOP: f32 = 0.5;
+
+// What evidence is worth when the level doesn't say, e.g. in ASCII maps
+pub const DEFAULT_EVIDENCE_POINTS: u32 = 100;
+
+// Define different tile types
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TileType {
+    Empty,
+    Platform,
+    Wall,
+    Evidence,
+    // Can be jumped up through and dropped down through with Down+Jump
+    OneWay,
+    // Climbed with Up and Down, without gravity
+    Ladder,
+    // Spikes, pits and the like. Touching one sends the player back to their last checkpoint.
+    Hazard,
+    // Touching one makes it the place the player comes back to after a hazard
+    Checkpoint,
+}
+
+// A simple 2D tile-based level
+pub struct Level {
+    pub width: usize,
+    pub height: usize,
+    pub tiles: Vec<TileType>,
+    // The perspective everywhere outside of perspective_zones
+    pub perspective: Perspective,
+    pub perspective_zones: Vec<PerspectiveZone>,
+    pub movement: LevelMovement,
+    pub spawn_point: (f32, f32),
+    // Reaching any of these finishes the level
+    pub exits: Vec<ExitZone>,
+    pub evidence: Vec<Evidence>,
+    pub triggers: Vec<Trigger>,
+    pub npcs: Vec<Npc>,
+    // Tiles changed by set_tile since the last take_changed_tiles, so
+    // renderers only rebuild what actually changed
+    changed_tiles: Vec<(usize, usize)>,
+}
+
+// A piece of evidence placed on an Evidence tile
+#[derive(Clone, Debug)]
+pub struct Evidence {
+    pub id: String,
+    // Tile coordinates
+    pub x: usize,
+    pub y: usize,
+    // Added to the player's score when collected
+    pub points: u32,
+}
+
+// An invisible area, in world pixels, that does something when the player walks into it
+#[derive(Clone, Debug)]
+pub struct Trigger {
+    pub name: String,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    // Shown in the dialogue box on entering
+    pub message: Option<String>,
+}
+
+impl Trigger {
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        rect_contains(self.x, self.y, self.width, self.height, x, y)
+    }
+}
+
+// Someone standing around in the level. Position is in world pixels.
+#[derive(Clone, Debug)]
+pub struct Npc {
+    pub name: String,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    // What they say when the player walks up to them
+    pub dialogue: Option<String>,
+}
+
+impl Level {
+    // Create a new empty level
+    pub fn new(width: usize, height: usize, perspective: Perspective) -> Self {
+        let tiles = vec![TileType::Empty; width * height];
+        Self {
+            width,
+            height,
+            tiles,
+            perspective,
+            perspective_zones: Vec::new(),
+            movement: LevelMovement::default(),
+            spawn_point: (0.0, 0.0),
+            exits: Vec::new(),
+            evidence: Vec::new(),
+            triggers: Vec::new(),
+            npcs: Vec::new(),
+            changed_tiles: Vec::new(),
+        }
+    }
+    
+    // Get a tile at a specific position
+    pub fn get_tile(&self, x: usize, y: usize) -> Option<&TileType> {
+        if x < self.width && y < self.height {
+            Some(&self.tiles[y * self.width + x])
+        } else {
+            None
+        }
+    }
+    
+    // Whether a tile blocks movement. Anything outside the level does.
+    pub fn is_solid(&self, x: usize, y: usize) -> bool {
+        match self.get_tile(x, y) {
+            Some(TileType::Platform) | Some(TileType::Wall) | None => true,
+            Some(TileType::Empty) | Some(TileType::Evidence) | Some(TileType::OneWay) |
+            Some(TileType::Ladder) | Some(TileType::Hazard) | Some(TileType::Checkpoint) => false,
+        }
+    }
+    
+    // The tile at a point in world pixels
+    pub fn tile_at(&self, x: f32, y: f32) -> Option<TileType> {
+        if x < 0.0 || y < 0.0 {
+            return None;
+        }
+        self.get_tile((x / TILE_SIZE) as usize, (y / TILE_SIZE) as usize).copied()
+    }
+    
+    // Whether a box in world pixels touches any spikes. They start HAZARD_TOP
+    // down their tile, so the bottom of the box has to reach that far into a
+    // hazard tile's row.
+    pub fn touches_hazard(&self, left: f32, top: f32, right: f32, bottom: f32) -> bool {
+        let spike_depth = HAZARD_TOP * TILE_SIZE;
+        self.find_tile(TileType::Hazard, left, top, right, bottom - spike_depth).is_some()
+    }
+    
+    // The first `tile` overlapping a box in world pixels, as (column, row).
+    // A box ending exactly on a tile edge doesn't touch the next tile.
+    pub fn find_tile(&self, tile: TileType, left: f32, top: f32, right: f32, bottom: f32) -> Option<(usize, usize)> {
+        let first_x = (left / TILE_SIZE).floor().max(0.0) as usize;
+        let first_y = (top / TILE_SIZE).floor().max(0.0) as usize;
+        let last_x = ((right / TILE_SIZE).ceil().max(0.0) as usize).min(self.width);
+        let last_y = ((bottom / TILE_SIZE).ceil().max(0.0) as usize).min(self.height);
+        
+        for y in first_y..last_y {
+            for x in first_x..last_x {
+                if self.tiles[y * self.width + x] == tile {
+                    return Some((x, y));
+                }
+            }
+        }
+        None
+    }
+    
+    // Where the player's centre goes to stand on the bottom of a tile, e.g. at
+    // the spawn point or a checkpoint
+    pub fn standing_position(x: usize, y: usize) -> (f32, f32) {
+        (
+            (x as f32 + 0.5) * TILE_SIZE,
+            (y as f32 + 1.0) * TILE_SIZE - PLAYER_HEIGHT / 2.0,
+        )
+    }
+    
+    // Set every tile a rectangle in world pixels touches
+    fn fill(&mut self, rect: &Platform, tile_type: TileType) {
+        let first_x = (rect.x / TILE_SIZE).floor().max(0.0) as usize;
+        let first_y = (rect.y / TILE_SIZE).floor().max(0.0) as usize;
+        let last_x = ((rect.x + rect.width) / TILE_SIZE).ceil() as usize;
+        let last_y = ((rect.y + rect.height) / TILE_SIZE).ceil() as usize;
+        for y in first_y..last_y {
+            for x in first_x..last_x {
+                self.set_tile(x, y, tile_type);
+            }
+        }
+    }
+    
+    // Set a tile at a specific position
+    pub fn set_tile(&mut self, x: usize, y: usize, tile_type: TileType) {
+        if x < self.width && y < self.height {
+            let index = y * self.width + x;
+            if self.tiles[index] != tile_type {
+                self.tiles[index] = tile_type;
+                self.changed_tiles.push((x, y));
+            }
+        }
+    }
+    
+    // Positions changed since the last call, clearing the list
+    pub fn take_changed_tiles(&mut self) -> Vec<(usize, usize)> {
+        std::mem::take(&mut self.changed_tiles)
+    }
+    
+    // The perspective at a point in world pixels. Later zones win where they overlap.
+    pub fn perspective_at(&self, x: f32, y: f32) -> Perspective {
+        self.perspective_zones.iter()
+            .rev()
+            .find(|zone| zone.contains(x, y))
+            .map_or(self.perspective, |zone| zone.perspective)
+    }
+    
+    // Set the spawn point
+    pub fn set_spawn_point(&mut self, x: f32, y: f32) {
+        self.spawn_point = (x, y);
+    }
+    
+    // Add a piece of evidence at a tile
+    pub fn add_evidence(&mut self, x: usize, y: usize, id: &str, points: u32) {
+        self.evidence.push(Evidence {
+            id: id.to_string(),
+            x,
+            y,
+            points,
+        });
+        // Also update the tile to be evidence, unless it's solid. Then the
+        // evidence can't be reached, which speech-lint reports.
+        if !self.is_solid(x, y) {
+            self.set_tile(x, y, TileType::Evidence);
+        }
+    }
+    
+    // The evidence on a tile, if any
+    pub fn evidence_at(&self, x: usize, y: usize) -> Option<&Evidence> {
+        self.evidence.iter().find(|evidence| evidence.x == x && evidence.y == y)
+    }
+    
+    // Load a level from a string representation. Every row has to be as wide
+    // as the first, and there has to be a spawn point.
+    pub fn from_string(data: &str, perspective: Perspective) -> Result<Self, LevelError> {
+        let lines = ascii_rows(data);
+        let height = lines.len();
+        let width = lines.first().ok_or(LevelError::Empty)?.chars().count();
+        
+        let mut level = Self::new(width, height, perspective);
+        let mut spawn_found = false;
+        
+        for (y, line) in lines.iter().enumerate() {
+            let found = line.chars().count();
+            if found != width {
+                return Err(LevelError::SizeMismatch {
+                    what: format!("row {}", y),
+                    expected: width,
+                    found,
+                });
+            }
+            
+            for (x, c) in line.chars().enumerate() {
+                match c {
+                    '#' => level.set_tile(x, y, TileType::Platform),
+                    'W' => level.set_tile(x, y, TileType::Wall),
+                    // ASCII maps can't name their evidence, so it's named after where it is
+                    'E' => level.add_evidence(x, y, &format!("evidence_{}_{}", x, y), DEFAULT_EVIDENCE_POINTS),
+                    '-' => level.set_tile(x, y, TileType::OneWay),
+                    'H' => level.set_tile(x, y, TileType::Ladder),
+                    '^' => level.set_tile(x, y, TileType::Hazard),
+                    'C' => level.set_tile(x, y, TileType::Checkpoint),
+                    'S' => {
+                        // The spawn point is the player's centre, so stand them on the bottom of the tile
+                        let (spawn_x, spawn_y) = Self::standing_position(x, y);
+                        level.set_spawn_point(spawn_x, spawn_y);
+                        level.set_tile(x, y, TileType::Empty);
+                        spawn_found = true;
+                    },
+                    // A one-tile exit that's always open
+                    'X' => {
+                        level.exits.push(ExitZone {
+                            x: x as f32 * TILE_SIZE,
+                            y: y as f32 * TILE_SIZE,
+                            width: TILE_SIZE,
+                            height: TILE_SIZE,
+                            required_evidence: Vec::new(),
+                        });
+                        level.set_tile(x, y, TileType::Empty);
+                    },
+                    '.' | ' ' => level.set_tile(x, y, TileType::Empty),
+                    glyph => return Err(LevelError::UnknownGlyph { glyph, x, y }),
+                }
+            }
+        }
+        
+        if !spawn_found {
+            return Err(LevelError::MissingSpawn);
+        }
+        Ok(level)
+    }
+    
+    // Build a level from its JSON description. Positions in the data are world
+    // pixels; platforms fill every tile their rectangle touches. The level
+    // starts at (0, 0), so nothing can be placed left of or above that.
+    pub fn from_data(data: &LevelData) -> Result<Self, LevelError> {
+        let mut positions = vec![
+            ("spawn point".to_string(), data.spawn_point),
+            ("exit point".to_string(), data.exit_point),
+        ];
+        let rects = [
+            ("platform", &data.platforms),
+            ("one-way platform", &data.one_way_platforms),
+            ("ladder", &data.ladders),
+            ("hazard", &data.hazards),
+        ];
+        for (what, list) in rects {
+            positions.extend(list.iter().map(|p| (what.to_string(), (p.x, p.y))));
+        }
+        positions.extend(data.checkpoints.iter().map(|&point| ("checkpoint".to_string(), point)));
+        positions.extend(data.evidence.iter().map(|e| (format!("evidence {}", e.id), (e.x, e.y))));
+        for (what, (x, y)) in positions {
+            if !(x >= 0.0 && y >= 0.0 && x.is_finite() && y.is_finite()) {
+                return Err(LevelError::OutOfBounds { what, x, y });
+            }
+        }
+        
+        // The data doesn't store a size, so make the level just big enough for everything in it
+        let mut right = data.spawn_point.0.max(data.exit_point.0);
+        let mut bottom = data.spawn_point.1.max(data.exit_point.1);
+        for (_, list) in rects {
+            for rect in list {
+                right = right.max(rect.x + rect.width);
+                bottom = bottom.max(rect.y + rect.height);
+            }
+        }
+        for &(x, y) in &data.checkpoints {
+            right = right.max(x + TILE_SIZE);
+            bottom = bottom.max(y + TILE_SIZE);
+        }
+        for evidence in &data.evidence {
+            right = right.max(evidence.x + TILE_SIZE);
+            bottom = bottom.max(evidence.y + TILE_SIZE);
+        }
+        let width = ((right / TILE_SIZE).ceil() as usize).max(1);
+        let height = ((bottom / TILE_SIZE).ceil() as usize).max(1);
+        
+        let mut level = Self::new(width, height, data.perspective);
+        level.perspective_zones = data.perspective_zones.clone();
+        level.movement = data.movement.clone();
+        
+        for platform in &data.platforms {
+            level.fill(platform, TileType::Platform);
+        }
+        for platform in &data.one_way_platforms {
+            level.fill(platform, TileType::OneWay);
+        }
+        for ladder in &data.ladders {
+            level.fill(ladder, TileType::Ladder);
+        }
+        for hazard in &data.hazards {
+            level.fill(hazard, TileType::Hazard);
+        }
+        // Checkpoints are the tile their point is in
+        for &(x, y) in &data.checkpoints {
+            level.set_tile((x / TILE_SIZE) as usize, (y / TILE_SIZE) as usize, TileType::Checkpoint);
+        }
+        
+        for evidence in &data.evidence {
+            let x = (evidence.x / TILE_SIZE).floor().max(0.0) as usize;
+            let y = (evidence.y / TILE_SIZE).floor().max(0.0) as usize;
+            level.add_evidence(x, y, &evidence.id, evidence.points);
+        }
+        
+        level.set_spawn_point(data.spawn_point.0, data.spawn_point.1);
+        
+        // exit_point is the tile it's in, gated on the level-wide requirements
+        level.exits.push(ExitZone {
+            x: (data.exit_point.0 / TILE_SIZE).floor() * TILE_SIZE,
+            y: (data.exit_point.1 / TILE_SIZE).floor() * TILE_SIZE,
+            width: TILE_SIZE,
+            height: TILE_SIZE,
+            required_evidence: data.required_evidence.clone(),
+        });
+        level.exits.extend(data.exits.iter().cloned());
+        
+        Ok(level)
+    }
+}
+
+// What the physics module collides boxes against
+impl TileGrid for Level {
+    fn tile_size(&self) -> f32 {
+        TILE_SIZE
+    }
+    
+    fn is_solid_at(&self, x: i32, y: i32) -> bool {
+        // Past the top or left edge is as solid as past the other two
+        if x < 0 || y < 0 {
+            return true;
+        }
+        self.is_solid(x as usize, y as usize)
+    }
+    
+    // The top of a ladder can be stood on too, so players can climb off it
+    fn is_one_way_at(&self, x: i32, y: i32) -> bool {
+        if x < 0 || y < 0 {
+            return false;
+        }
+        let (x, y) = (x as usize, y as usize);
+        match self.get_tile(x, y) {
+            Some(TileType::OneWay) => true,
+            Some(TileType::Ladder) => y == 0 || self.get_tile(x, y - 1) != Some(&TileType::Ladder),
+            _ => false,
+        }
+    }
+}
+
+// The kinds of level file we can load, by extension
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LevelFormat {
+    Json,
+    Tiled,
+    Ascii,
+}
+
+impl LevelFormat {
+    pub fn of(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "json" => Some(LevelFormat::Json),
+            "tmj" => Some(LevelFormat::Tiled),
+            "txt" => Some(LevelFormat::Ascii),
+            _ => None,
+        }
+    }
+}
+
+// Load one level file of any format. Returns the level's name along with it.
+// ASCII maps are named after the file and always side-scrolling.
+pub fn load_level_path(path: &Path) -> Result<(String, Level), LevelError> {
+    match LevelFormat::of(path) {
+        Some(LevelFormat::Json) => {
+            let data = loader::load_level_file(path)?;
+            let level = Level::from_data(&data)
+                .map_err(|e| e.in_file(path))?;
+            Ok((data.name, level))
+        },
+        Some(LevelFormat::Tiled) => tiled::load_map(path),
+        Some(LevelFormat::Ascii) => {
+            let text = fs::read_to_string(path)
+                .map_err(|error| LevelError::Io { path: path.to_path_buf(), error })?;
+            let level = Level::from_string(&text, Perspective::SideScrolling)
+                .map_err(|e| e.in_file(path))?;
+            Ok((name_from_path(path), level))
+        },
+        None => Err(LevelError::Invalid(format!("{} isn't a level file", path.display()))),
+    }
+}
+
+// The rows of an ASCII map. Empty lines before and after it are dropped, but
+// spaces are empty tiles, so they're kept even at the start or end of a row.
+pub fn ascii_rows(text: &str) -> Vec<&str> {
+    let lines: Vec<&str> = text.lines().collect();
+    let first = lines.iter().position(|line| !line.is_empty()).unwrap_or(lines.len());
+    let last = lines.iter().rposition(|line| !line.is_empty()).map_or(first, |last| last + 1);
+    lines[first..last].to_vec()
+}
+
+// A level's name when the file doesn't give it one
+pub fn name_from_path(path: &Path) -> String {
+    path.file_stem()
+        .map_or_else(|| path.display().to_string(), |stem| stem.to_string_lossy().into_owned())
+}
+
+// A collection of levels
+#[derive(Default)]
+pub struct World {
+    pub levels: HashMap<String, Level>,
+    pub current_level: String,
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    // Load every level in a directory: our own *.json levels, Tiled *.tmj maps
+    // and ASCII *.txt maps. The first file (by name) is the starting level.
+    pub fn load_dir(dir: &Path) -> Result<Self, LevelError> {
+        let entries = fs::read_dir(dir)
+            .map_err(|error| LevelError::Io { path: dir.to_path_buf(), error })?;
+        
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| LevelFormat::of(path).is_some())
+            .collect();
+        paths.sort();
+        
+        let mut world = Self::new();
+        for path in &paths {
+            let (name, level) = load_level_path(path)?;
+            if world.levels.contains_key(&name) {
+                return Err(LevelError::DuplicateName { name, path: path.clone() });
+            }
+            world.add_level(&name, level);
+        }
+        
+        if world.levels.is_empty() {
+            return Err(LevelError::NoLevels { dir: dir.to_path_buf() });
+        }
+        Ok(world)
+    }
+    
+    // Check that every level in a campaign exists
+    pub fn check_campaign(&self, campaign: &CampaignData) -> Result<(), LevelError> {
+        if campaign.levels.is_empty() {
+            return Err(LevelError::Invalid(format!("Campaign {} has no levels", campaign.name)));
+        }
+        for name in &campaign.levels {
+            if !self.levels.contains_key(name) {
+                return Err(LevelError::Invalid(format!("Campaign {} refers to unknown level {}", campaign.name, name)));
+            }
+        }
+        Ok(())
+    }
+    
+    // Add a level to the world
+    pub fn add_level(&mut self, name: &str, level: Level) {
+        self.levels.insert(name.to_string(), level);
+        if self.current_level.is_empty() {
+            self.current_level = name.to_string();
+        }
+    }
+    
+    // Switch to a different level
+    pub fn switch_level(&mut self, name: &str) -> bool {
+        if self.levels.contains_key(name) {
+            self.current_level = name.to_string();
+            true
+        } else {
+            false
+        }
+    }
+    
+    // Names of all the levels, sorted so menus list them in a stable order
+    pub fn level_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.levels.keys().cloned().collect();
+        names.sort();
+        names
+    }
+    
+    // Get the current level
+    pub fn current_level(&self) -> Option<&Level> {
+        self.levels.get(&self.current_level)
+    }
+    
+    // Get a mutable reference to the current level
+    pub fn current_level_mut(&mut self) -> Option<&mut Level> {
+        self.levels.get_mut(&self.current_level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::entities::player::PLAYER_WIDTH;
+
+    fn load(rows: &[&str]) -> Result<Level, LevelError> {
+        Level::from_string(&rows.join("\n"), Perspective::SideScrolling)
+    }
+
+    #[test]
+    fn spaces_at_the_edges_of_the_map_are_empty_tiles() {
+        let level = load(&[
+            "   S",
+            "####",
+            "#.  ",
+        ]).expect("Failed to load map");
+        assert_eq!((level.width, level.height), (4, 3));
+        assert_eq!(level.get_tile(0, 0), Some(&TileType::Empty));
+        assert_eq!(level.get_tile(3, 2), Some(&TileType::Empty));
+        assert_eq!(level.get_tile(0, 2), Some(&TileType::Platform));
+    }
+
+    #[test]
+    fn blank_lines_around_the_map_are_ignored() {
+        let level = Level::from_string("\n\nS.\n##\n\n", Perspective::SideScrolling)
+            .expect("Failed to load map");
+        assert_eq!((level.width, level.height), (2, 2));
+        assert_eq!(level.get_tile(0, 1), Some(&TileType::Platform));
+    }
+
+    #[test]
+    fn ragged_rows_are_reported_by_row() {
+        let error = load(&["S..", "##"]).err().expect("Ragged map loaded");
+        assert!(matches!(error, LevelError::SizeMismatch { ref what, expected: 3, found: 2 } if what == "row 1"),
+                "{:?}", error);
+    }
+
+    #[test]
+    fn unknown_glyphs_are_reported_where_they_are() {
+        let error = load(&["S..", "#?#"]).err().expect("Map with an unknown glyph loaded");
+        assert!(matches!(error, LevelError::UnknownGlyph { glyph: '?', x: 1, y: 1 }), "{:?}", error);
+    }
+
+    #[test]
+    fn a_map_needs_a_spawn_point() {
+        let error = load(&["...", "###"]).err().expect("Map without a spawn loaded");
+        assert!(matches!(error, LevelError::MissingSpawn), "{:?}", error);
+    }
+
+    #[test]
+    fn an_empty_map_is_an_error() {
+        let error = Level::from_string("\n\n", Perspective::SideScrolling).err().expect("Empty map loaded");
+        assert!(matches!(error, LevelError::Empty), "{:?}", error);
+    }
+
+    // The player's box in world pixels with their feet at (x, feet)
+    fn player_box(x: f32, feet: f32) -> (f32, f32, f32, f32) {
+        (x - PLAYER_WIDTH / 2.0, feet - PLAYER_HEIGHT, x + PLAYER_WIDTH / 2.0, feet)
+    }
+
+    #[test]
+    fn standing_right_next_to_spikes_is_safe() {
+        let level = load(&["S^.", "###"]).expect("Failed to load map");
+        let (left, top, right, bottom) = player_box(TILE_SIZE - PLAYER_WIDTH / 2.0, TILE_SIZE);
+        assert!(!level.touches_hazard(left, top, right, bottom));
+    }
+
+    #[test]
+    fn only_the_spikes_in_a_hazard_tile_hurt() {
+        let level = load(&["...", "S^.", "###"]).expect("Failed to load map");
+        let spikes = TILE_SIZE * (1.0 + HAZARD_TOP);
+
+        // Clearing the spikes, even with the feet inside the hazard tile
+        let (left, top, right, bottom) = player_box(1.5 * TILE_SIZE, spikes - 1.0);
+        assert_eq!(level.tile_at(1.5 * TILE_SIZE, bottom - 1.0), Some(TileType::Hazard));
+        assert!(!level.touches_hazard(left, top, right, bottom));
+
+        let (left, top, right, bottom) = player_box(1.5 * TILE_SIZE, spikes + 1.0);
+        assert!(level.touches_hazard(left, top, right, bottom));
+    }
+}
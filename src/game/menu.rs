@@ -0,0 +1,112 @@
+use crate::engine::graphics::Renderer;
+use crate::engine::text::{TextAlign, TextSpan, TextStyle};
+use winit::event::VirtualKeyCode;
+
+const ITEM_COLOR: [f32; 4] = [0.85, 0.85, 0.85, 1.0];
+const SELECTED_COLOR: [f32; 4] = [1.0, 0.85, 0.3, 1.0];
+const DISABLED_COLOR: [f32; 4] = [0.15, 0.15, 0.15, 1.0];
+
+// Extra space between items, in pixels
+const ITEM_SPACING: f32 = 12.0;
+
+pub struct MenuItem<T> {
+    pub label: String,
+    pub action: T,
+    // Disabled items are greyed out and skipped when moving the selection
+    pub enabled: bool,
+}
+
+// A vertical list of options picked with the arrow keys and Enter.
+// `T` is whatever the owning state wants back when an item is chosen.
+pub struct Menu<T> {
+    items: Vec<MenuItem<T>>,
+    selected: usize,
+}
+
+impl<T: Clone> Default for Menu<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> Menu<T> {
+    pub fn new() -> Self {
+        Self {
+            items: Vec::new(),
+            selected: 0,
+        }
+    }
+
+    // Add an item to the bottom of the menu
+    pub fn add(&mut self, label: &str, action: T, enabled: bool) {
+        self.items.push(MenuItem {
+            label: label.to_string(),
+            action,
+            enabled,
+        });
+
+        // Never leave the cursor on something that can't be chosen
+        if !self.items[self.selected].enabled {
+            self.move_selection(1);
+        }
+    }
+
+    // Move the cursor by `step` items, wrapping around and skipping disabled ones
+    fn move_selection(&mut self, step: isize) {
+        let count = self.items.len() as isize;
+        let mut index = self.selected as isize;
+        for _ in 0..count {
+            index = (index + step).rem_euclid(count);
+            if self.items[index as usize].enabled {
+                self.selected = index as usize;
+                return;
+            }
+        }
+    }
+
+    // Up/Down (or W/S) move the cursor, Enter or Space choose.
+    // Returns the chosen item's action.
+    pub fn handle_key(&mut self, key: VirtualKeyCode) -> Option<T> {
+        match key {
+            VirtualKeyCode::Up | VirtualKeyCode::W => {
+                self.move_selection(-1);
+                None
+            },
+            VirtualKeyCode::Down | VirtualKeyCode::S => {
+                self.move_selection(1);
+                None
+            },
+            VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter | VirtualKeyCode::Space => {
+                self.items.get(self.selected)
+                    .filter(|item| item.enabled)
+                    .map(|item| item.action.clone())
+            },
+            _ => None,
+        }
+    }
+
+    // Queue the menu centred in a column `width` pixels wide starting at `x`.
+    // Returns the height it took up.
+    pub fn render(&self, renderer: &mut Renderer, font_id: &str, x: f32, y: f32, width: f32) -> f32 {
+        let style = TextStyle {
+            max_width: Some(width),
+            align: TextAlign::Center,
+        };
+
+        let mut cursor_y = y;
+        for (i, item) in self.items.iter().enumerate() {
+            let span = if !item.enabled {
+                TextSpan::new(item.label.as_str(), DISABLED_COLOR)
+            } else if i == self.selected {
+                TextSpan::new(format!("> {} <", item.label), SELECTED_COLOR)
+            } else {
+                TextSpan::new(item.label.as_str(), ITEM_COLOR)
+            };
+
+            let (_, height) = renderer.push_text(font_id, &[span], x, cursor_y, &style);
+            cursor_y += height + ITEM_SPACING;
+        }
+
+        cursor_y - y
+    }
+}
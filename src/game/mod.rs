@@ -1,3 +1,6 @@
 pub mod states;
 pub mod entities;
-pub mod level;
\ No newline at end of file
+pub mod level;
+pub mod tilemap;
+pub mod menu;
+pub mod screen;
\ No newline at end of file
@@ -0,0 +1,101 @@
+// src/game/screen.rs
+use crate::engine::graphics::{Renderer, Space};
+use crate::engine::camera::Camera2D;
+use crate::game::states::playing::FONT_PATH;
+use winit::event::{WindowEvent, VirtualKeyCode, ElementState, KeyboardInput};
+use wgpu::{Device, Queue, SurfaceConfiguration, TextureView};
+use std::cell::RefCell;
+use std::fs;
+use std::rc::Rc;
+
+// Dark blue, like the sky before dawn. Behind the title and between levels.
+pub const BACKGROUND_COLOR: wgpu::Color = wgpu::Color {
+    r: 0.01,
+    g: 0.015,
+    b: 0.04,
+    a: 1.0,
+};
+
+// Fonts every menu screen can use, all cut from the same UI font
+pub const LOGO_FONT: &str = "logo";
+const LOGO_FONT_SIZE: f32 = 72.0;
+pub const HEADING_FONT: &str = "heading";
+const HEADING_FONT_SIZE: f32 = 48.0;
+pub const MENU_FONT: &str = "menu";
+pub const MENU_FONT_SIZE: f32 = 28.0;
+pub const TEXT_FONT: &str = "text";
+const TEXT_FONT_SIZE: f32 = 20.0;
+
+// The renderer and fonts behind the title, pause, level complete and error
+// screens. A renderer means a shader, a pipeline and buffers, and every font
+// has to be rasterized, so this is made once at startup and handed from
+// screen to screen instead of each screen building its own.
+pub struct ScreenRenderer {
+    renderer: Renderer,
+}
+
+pub type SharedScreenRenderer = Rc<RefCell<ScreenRenderer>>;
+
+impl ScreenRenderer {
+    pub fn new(device: &Device, queue: &Queue, format: wgpu::TextureFormat) -> SharedScreenRenderer {
+        let mut renderer = Renderer::new(device, queue, format);
+
+        let font_bytes = fs::read(FONT_PATH)
+            .expect("Failed to read UI font");
+        let fonts = [
+            (LOGO_FONT, LOGO_FONT_SIZE),
+            (HEADING_FONT, HEADING_FONT_SIZE),
+            (MENU_FONT, MENU_FONT_SIZE),
+            (TEXT_FONT, TEXT_FONT_SIZE),
+        ];
+        for (id, size) in fonts {
+            renderer.load_font(device, queue, id, &font_bytes, size)
+                .expect("Failed to load UI font");
+        }
+
+        Rc::new(RefCell::new(Self { renderer }))
+    }
+
+    pub fn set_format(&mut self, device: &Device, format: wgpu::TextureFormat) {
+        self.renderer.set_format(device, format);
+    }
+
+    // Draw a screen in screen pixels, with (0, 0) at the top left. `draw` gets
+    // the renderer and the size of the screen. Pass a clear colour for a screen
+    // of its own, or None to draw over whatever is underneath.
+    pub fn draw<F>(&mut self,
+                   device: &Device,
+                   queue: &Queue,
+                   view: &TextureView,
+                   config: &SurfaceConfiguration,
+                   clear_color: Option<wgpu::Color>,
+                   draw: F)
+    where
+        F: FnOnce(&mut Renderer, f32, f32),
+    {
+        let width = config.width as f32;
+        let height = config.height as f32;
+        self.renderer.set_camera(queue, &Camera2D::new(width, height));
+
+        self.renderer.begin_batch();
+        self.renderer.set_space(Space::Screen);
+        draw(&mut self.renderer, width, height);
+        self.renderer.flush(device, queue, view, clear_color);
+    }
+}
+
+// The key that was just pressed, if the event is a key press. Menu screens
+// only care about presses.
+pub fn pressed_key(event: &WindowEvent) -> Option<VirtualKeyCode> {
+    match event {
+        WindowEvent::KeyboardInput {
+            input: KeyboardInput {
+                state: ElementState::Pressed,
+                virtual_keycode: Some(keycode),
+                ..
+            },
+            ..
+        } => Some(*keycode),
+        _ => None,
+    }
+}
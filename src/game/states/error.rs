@@ -0,0 +1,125 @@
+// src/game/states/error.rs
+use crate::engine::state::{GameState, Transition};
+use crate::engine::text::{TextAlign, TextSpan, TextStyle};
+use crate::game::menu::Menu;
+use crate::game::screen::{self, SharedScreenRenderer, HEADING_FONT, MENU_FONT, MENU_FONT_SIZE, TEXT_FONT};
+use crate::game::states::title::TitleState;
+use crate::levels::error::LevelError;
+use winit::event::{WindowEvent, VirtualKeyCode};
+use wgpu::{Device, Queue, SurfaceConfiguration, TextureView};
+
+// Dark red, so it's obvious something went wrong
+const ERROR_BACKGROUND_COLOR: wgpu::Color = wgpu::Color {
+    r: 0.04,
+    g: 0.005,
+    b: 0.005,
+    a: 1.0,
+};
+
+const HEADING_COLOR: [f32; 4] = [1.0, 0.4, 0.3, 1.0];
+const TEXT_COLOR: [f32; 4] = [0.85, 0.85, 0.85, 1.0];
+
+// Space left either side of the error message
+const MARGIN: f32 = 40.0;
+
+#[derive(Clone)]
+enum ErrorAction {
+    Retry,
+    Quit,
+}
+
+// Shown instead of crashing when the levels can't be loaded, so designers can
+// see what's wrong, fix the file and try again without restarting the game.
+pub struct ErrorState {
+    screen: SharedScreenRenderer,
+    menu: Menu<ErrorAction>,
+    message: String,
+}
+
+impl ErrorState {
+    pub fn new(screen: SharedScreenRenderer, error: &LevelError) -> Self {
+        // Also in the log, where it can be copied from
+        eprintln!("Failed to load levels: {}", error);
+
+        let mut menu = Menu::new();
+        menu.add("Retry", ErrorAction::Retry, true);
+        menu.add("Quit", ErrorAction::Quit, true);
+
+        Self {
+            screen,
+            menu,
+            message: error.to_string(),
+        }
+    }
+
+    fn handle_key(&mut self, key: VirtualKeyCode) -> Transition {
+        match self.menu.handle_key(key) {
+            // Load everything again from the title screen
+            Some(ErrorAction::Retry) => {
+                let screen = self.screen.clone();
+                Transition::reset(move |_, _, _| {
+                    or_error_screen(&screen, TitleState::new(screen.clone()))
+                })
+            },
+            Some(ErrorAction::Quit) => Transition::Quit,
+            None => Transition::None,
+        }
+    }
+}
+
+// The state if it was built, otherwise an error screen saying why not
+pub fn or_error_screen<S: GameState + 'static>(screen: &SharedScreenRenderer,
+                                               state: Result<S, LevelError>) -> Box<dyn GameState> {
+    match state {
+        Ok(state) => Box::new(state),
+        Err(error) => Box::new(ErrorState::new(screen.clone(), &error)),
+    }
+}
+
+impl GameState for ErrorState {
+    fn handle_event(&mut self, event: &WindowEvent) -> Transition {
+        match screen::pressed_key(event) {
+            Some(key) => self.handle_key(key),
+            None => Transition::None,
+        }
+    }
+
+    fn resize(&mut self, device: &Device, config: &SurfaceConfiguration) {
+        self.screen.borrow_mut().set_format(device, config.format);
+    }
+
+    fn render(&mut self, device: &Device, queue: &Queue, view: &TextureView,
+              config: &SurfaceConfiguration, _alpha: f32) {
+        self.screen.borrow_mut().draw(device, queue, view, config, Some(ERROR_BACKGROUND_COLOR), |renderer, width, height| {
+            let centred = TextStyle {
+                max_width: Some(width),
+                align: TextAlign::Center,
+            };
+            let mut y = height * 0.15;
+            let (_, heading_height) = renderer.push_text(
+                HEADING_FONT,
+                &[TextSpan::new("Couldn't load the levels", HEADING_COLOR)],
+                0.0,
+                y,
+                &centred
+            );
+            y += heading_height * 1.5;
+
+            // File paths can be long, so wrap the message inside the margins
+            let wrapped = TextStyle {
+                max_width: Some(width - MARGIN * 2.0),
+                align: TextAlign::Left,
+            };
+            let (_, message_height) = renderer.push_text(
+                TEXT_FONT,
+                &[TextSpan::new(self.message.as_str(), TEXT_COLOR)],
+                MARGIN,
+                y,
+                &wrapped
+            );
+            y += message_height + MENU_FONT_SIZE * 2.0;
+
+            self.menu.render(renderer, MENU_FONT, 0.0, y, width);
+        });
+    }
+}
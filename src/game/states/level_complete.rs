@@ -0,0 +1,136 @@
+// src/game/states/level_complete.rs
+use crate::ecs::components::Inventory;
+use crate::engine::state::{GameState, Transition};
+use crate::engine::text::{TextAlign, TextSpan, TextStyle};
+use crate::game::menu::Menu;
+use crate::game::screen::{self, SharedScreenRenderer, BACKGROUND_COLOR, HEADING_FONT, MENU_FONT, MENU_FONT_SIZE};
+use crate::game::states::playing::PlayingState;
+use crate::game::states::title::TitleState;
+use crate::game::states::error::or_error_screen;
+use winit::event::{WindowEvent, VirtualKeyCode};
+use wgpu::{Device, Queue, SurfaceConfiguration, TextureView};
+
+const HEADING_COLOR: [f32; 4] = [1.0, 0.85, 0.3, 1.0];
+const TEXT_COLOR: [f32; 4] = [0.7, 0.75, 0.85, 1.0];
+const VALUE_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+// How the player did on the level they just finished
+pub struct LevelSummary {
+    pub level: String,
+    pub evidence_found: usize,
+    pub evidence_total: usize,
+    // Points from this level's evidence
+    pub level_score: u32,
+    // Points over the whole campaign so far
+    pub total_score: u32,
+    // None once the campaign is over
+    pub next_level: Option<String>,
+}
+
+#[derive(Clone)]
+enum CompleteAction {
+    NextLevel,
+    QuitToTitle,
+}
+
+// Shown between levels. Continuing builds a fresh PlayingState on the next
+// level of the campaign, carrying the score over.
+pub struct LevelCompleteState {
+    screen: SharedScreenRenderer,
+    menu: Menu<CompleteAction>,
+    summary: LevelSummary,
+}
+
+impl LevelCompleteState {
+    pub fn new(screen: SharedScreenRenderer, summary: LevelSummary) -> Self {
+        let mut menu = Menu::new();
+        if summary.next_level.is_some() {
+            menu.add("Next Level", CompleteAction::NextLevel, true);
+        }
+        menu.add("Quit to Title", CompleteAction::QuitToTitle, true);
+
+        Self {
+            screen,
+            menu,
+            summary,
+        }
+    }
+
+    fn handle_key(&mut self, key: VirtualKeyCode) -> Transition {
+        match self.menu.handle_key(key) {
+            Some(CompleteAction::NextLevel) => {
+                let Some(level) = self.summary.next_level.clone() else { return Transition::None };
+                let score = self.summary.total_score;
+                let screen = self.screen.clone();
+                Transition::replace(move |device, queue, config| {
+                    let playing = PlayingState::new(device, queue, config.format, screen.clone()).map(|mut playing| {
+                        playing.set_inventory(Inventory { score, ..Inventory::default() });
+                        playing.switch_level(&level);
+                        playing
+                    });
+                    or_error_screen(&screen, playing)
+                })
+            },
+            Some(CompleteAction::QuitToTitle) => {
+                let screen = self.screen.clone();
+                Transition::reset(move |_, _, _| {
+                    or_error_screen(&screen, TitleState::new(screen.clone()))
+                })
+            },
+            None => Transition::None,
+        }
+    }
+}
+
+impl GameState for LevelCompleteState {
+    fn handle_event(&mut self, event: &WindowEvent) -> Transition {
+        match screen::pressed_key(event) {
+            Some(key) => self.handle_key(key),
+            None => Transition::None,
+        }
+    }
+
+    fn resize(&mut self, device: &Device, config: &SurfaceConfiguration) {
+        self.screen.borrow_mut().set_format(device, config.format);
+    }
+
+    fn render(&mut self, device: &Device, queue: &Queue, view: &TextureView,
+              config: &SurfaceConfiguration, _alpha: f32) {
+        self.screen.borrow_mut().draw(device, queue, view, config, Some(BACKGROUND_COLOR), |renderer, width, height| {
+            let centred = TextStyle {
+                max_width: Some(width),
+                align: TextAlign::Center,
+            };
+
+            let heading = if self.summary.next_level.is_some() { "Level Complete" } else { "Case Closed" };
+            let mut y = height * 0.15;
+            let (_, heading_height) = renderer.push_text(
+                HEADING_FONT,
+                &[TextSpan::new(heading, HEADING_COLOR)],
+                0.0,
+                y,
+                &centred
+            );
+            y += heading_height * 1.5;
+
+            let lines = [
+                ("Level: ", self.summary.level.clone()),
+                ("Evidence found: ", format!("{}/{}", self.summary.evidence_found, self.summary.evidence_total)),
+                ("Points this level: ", self.summary.level_score.to_string()),
+                ("Total score: ", self.summary.total_score.to_string()),
+            ];
+            for (label, value) in lines {
+                let (_, line_height) = renderer.push_text(
+                    MENU_FONT,
+                    &[TextSpan::new(label, TEXT_COLOR), TextSpan::new(value, VALUE_COLOR)],
+                    0.0,
+                    y,
+                    &centred
+                );
+                y += line_height;
+            }
+
+            self.menu.render(renderer, MENU_FONT, 0.0, y + MENU_FONT_SIZE * 2.0, width);
+        });
+    }
+}
@@ -1,2 +1,5 @@
 pub mod playing;
-pub mod title;
\ No newline at end of file
+pub mod title;
+pub mod pause;
+pub mod level_complete;
+pub mod error;
\ No newline at end of file
@@ -0,0 +1,125 @@
+// src/game/states/pause.rs
+use crate::ecs::components::Inventory;
+use crate::engine::state::{GameState, Transition};
+use crate::engine::text::{TextAlign, TextSpan, TextStyle};
+use crate::game::menu::Menu;
+use crate::game::screen::{self, SharedScreenRenderer, HEADING_FONT, MENU_FONT};
+use crate::game::states::playing::PlayingState;
+use crate::game::states::title::TitleState;
+use crate::game::states::error::or_error_screen;
+use winit::event::{WindowEvent, VirtualKeyCode};
+use wgpu::{Device, Queue, SurfaceConfiguration, TextureView};
+
+// Drawn over the whole frame so the paused game shows through, darker
+const DIM_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+const HEADING_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+#[derive(Clone)]
+enum PauseAction {
+    Resume,
+    Restart,
+    Settings,
+    QuitToTitle,
+}
+
+// Sits on top of PlayingState while the game is paused. The game underneath
+// keeps rendering but isn't updated, since only the top state is.
+pub struct PauseState {
+    screen: SharedScreenRenderer,
+    menu: Menu<PauseAction>,
+    // The level being played, and the evidence and score the player had
+    // when they got there, for Restart Level
+    level: String,
+    level_start: Inventory,
+}
+
+impl PauseState {
+    pub fn new(screen: SharedScreenRenderer, level: String, level_start: Inventory) -> Self {
+        let mut menu = Menu::new();
+        menu.add("Resume", PauseAction::Resume, true);
+        menu.add("Restart Level", PauseAction::Restart, true);
+        // There are no settings yet, so this can't be chosen
+        menu.add("Settings", PauseAction::Settings, false);
+        menu.add("Quit to Title", PauseAction::QuitToTitle, true);
+
+        Self {
+            screen,
+            menu,
+            level,
+            level_start,
+        }
+    }
+
+    fn handle_key(&mut self, key: VirtualKeyCode) -> Transition {
+        // Escape again goes straight back to the game
+        if key == VirtualKeyCode::Escape {
+            return Transition::Pop;
+        }
+
+        match self.menu.handle_key(key) {
+            Some(PauseAction::Resume) => Transition::Pop,
+            Some(PauseAction::Restart) => {
+                // A fresh game on the same level, replacing the paused one
+                // underneath. Only what was picked up on this level is lost.
+                let level = self.level.clone();
+                let level_start = self.level_start.clone();
+                let screen = self.screen.clone();
+                Transition::reset(move |device, queue, config| {
+                    let playing = PlayingState::new(device, queue, config.format, screen.clone()).map(|mut playing| {
+                        playing.set_inventory(level_start);
+                        playing.switch_level(&level);
+                        playing
+                    });
+                    or_error_screen(&screen, playing)
+                })
+            },
+            Some(PauseAction::QuitToTitle) => {
+                let screen = self.screen.clone();
+                Transition::reset(move |_, _, _| {
+                    or_error_screen(&screen, TitleState::new(screen.clone()))
+                })
+            },
+            Some(PauseAction::Settings) | None => Transition::None,
+        }
+    }
+}
+
+impl GameState for PauseState {
+    fn handle_event(&mut self, event: &WindowEvent) -> Transition {
+        match screen::pressed_key(event) {
+            Some(key) => self.handle_key(key),
+            None => Transition::None,
+        }
+    }
+
+    fn resize(&mut self, device: &Device, config: &SurfaceConfiguration) {
+        self.screen.borrow_mut().set_format(device, config.format);
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+
+    fn render(&mut self, device: &Device, queue: &Queue, view: &TextureView,
+              config: &SurfaceConfiguration, _alpha: f32) {
+        // Draw over the paused game instead of clearing it
+        self.screen.borrow_mut().draw(device, queue, view, config, None, |renderer, width, height| {
+            renderer.push_rect(0.0, 0.0, width, height, DIM_COLOR);
+
+            let centred = TextStyle {
+                max_width: Some(width),
+                align: TextAlign::Center,
+            };
+            let heading_y = height * 0.25;
+            let (_, heading_height) = renderer.push_text(
+                HEADING_FONT,
+                &[TextSpan::new("Paused", HEADING_COLOR)],
+                0.0,
+                heading_y,
+                &centred
+            );
+
+            self.menu.render(renderer, MENU_FONT, 0.0, heading_y + heading_height * 2.0, width);
+        });
+    }
+}
@@ -1,321 +1,682 @@
-// src/game/states/playing.rs
-use crate::engine::state::GameState;
-use crate::engine::graphics::Renderer;
-use crate::game::entities::player::Player;
-use crate::game::level::{World, Level, TileType, Perspective};
-use winit::event::{WindowEvent, VirtualKeyCode, ElementState, KeyboardInput};
-use wgpu::{Device, Queue, Surface, SurfaceConfiguration};
-use std::path::Path;
-use std::fs;
-
-pub struct PlayingState {
-    player: Player,
-    renderer: Renderer,
-    world: World,
-    camera_x: f32,
-    camera_y: f32,
-    assets_loaded: bool,
-}
-
-impl PlayingState {
-    pub fn new(device: &Device, queue: &Queue) -> Self {
-        let mut renderer = Renderer::new(device);
-        let player = Player::new(100.0, 300.0);
-        let mut world = World::new();
-        
-        // Create a test level for now
-        let test_level_data = "
-####################
-#                  #
-#                  #
-#     E            #
-#   #####          #
-#                  #
-#         S        #
-#                  #
-#                  #
-#       #####      #
-#                  #
-#                  #
-#                 E#
-####################
-";
-        let test_level = Level::from_string(test_level_data, Perspective::SideScrolling);
-        world.add_level("test_level", test_level);
-        
-        // Create another level with a top-down perspective
-        let topdown_level_data = "
-####################
-#                  #
-#     E            #
-#                  #
-#   #####          #
-#                  #
-#         S        #
-#        ###       #
-#         #        #
-#       #####      #
-#                  #
-#            E     #
-#                  #
-####################
-";
-        let topdown_level = Level::from_string(topdown_level_data, Perspective::TopDown);
-        world.add_level("topdown_level", topdown_level);
-        
-        let mut state = Self {
-            player,
-            renderer,
-            world,
-            camera_x: 0.0,
-            camera_y: 0.0,
-            assets_loaded: false,
-        };
-        
-        // Initialize the player position based on the level's spawn point
-        if let Some(level) = state.world.current_level() {
-            state.player.x = level.spawn_point.0;
-            state.player.y = level.spawn_point.1;
-        }
-        
-        state
-    }
-    
-    pub fn new_empty() -> Self {
-        Self {
-            player: Player::new(0.0, 0.0),
-            renderer: Renderer::new_empty(),
-            world: World::new(),
-            camera_x: 0.0,
-            camera_y: 0.0,
-            assets_loaded: false,
-        }
-    }
-    
-    // Load game assets
-    pub fn load_assets(&mut self, device: &Device, queue: &Queue) {
-        if self.assets_loaded {
-            return;
-        }
-        
-        // Placeholder for loading player sprite
-        // In a real game, you'd load texture files from disk
-        let player_sprite_bytes = include_bytes!("../../../assets/player.png");
-        self.renderer.load_texture(device, queue, "player", player_sprite_bytes)
-            .expect("Failed to load player texture");
-        
-        // Load tile textures
-        let platform_sprite_bytes = include_bytes!("../../../assets/platform.png");
-        self.renderer.load_texture(device, queue, "platform", platform_sprite_bytes)
-            .expect("Failed to load platform texture");
-        
-        let evidence_sprite_bytes = include_bytes!("../../../assets/evidence.png");
-        self.renderer.load_texture(device, queue, "evidence", evidence_sprite_bytes)
-            .expect("Failed to load evidence texture");
-        
-        self.assets_loaded = true;
-    }
-    
-    // Update camera position to follow the player
-    fn update_camera(&mut self, screen_width: f32, screen_height: f32) {
-        // Target position is the player
-        let target_x = self.player.x - screen_width / 2.0;
-        let target_y = self.player.y - screen_height / 2.0;
-        
-        // Smoothly move the camera towards the target
-        self.camera_x += (target_x - self.camera_x) * 0.1;
-        self.camera_y += (target_y - self.camera_y) * 0.1;
-        
-        // Ensure the camera doesn't go outside the level boundaries
-        if let Some(level) = self.world.current_level() {
-            let level_width = level.width as f32 * 32.0; // 32 pixels per tile
-            let level_height = level.height as f32 * 32.0;
-            
-            if self.camera_x < 0.0 {
-                self.camera_x = 0.0;
-            } else if self.camera_x > level_width - screen_width {
-                self.camera_x = level_width - screen_width;
-            }
-            
-            if self.camera_y < 0.0 {
-                self.camera_y = 0.0;
-            } else if self.camera_y > level_height - screen_height {
-                self.camera_y = level_height - screen_height;
-            }
-        }
-    }
-}
-
-impl GameState for PlayingState {
-    fn handle_event(&mut self, event: &WindowEvent) -> bool {
-        match event {
-            WindowEvent::KeyboardInput { 
-                input: KeyboardInput {
-                    state, 
-                    virtual_keycode: Some(keycode),
-                    ..
-                },
-                ..
-            } => {
-                match (keycode, state) {
-                    (VirtualKeyCode::Escape, ElementState::Pressed) => {
-                        // Exit on Escape
-                        return true;
-                    },
-                    (VirtualKeyCode::Left, ElementState::Pressed) => {
-                        self.player.move_left(true);
-                    },
-                    (VirtualKeyCode::Right, ElementState::Pressed) => {
-                        self.player.move_right(true);
-                    },
-                    (VirtualKeyCode::Left, ElementState::Released) => {
-                        self.player.move_left(false);
-                    },
-                    (VirtualKeyCode::Right, ElementState::Released) => {
-                        self.player.move_right(false);
-                    },
-                    (VirtualKeyCode::Up, ElementState::Pressed) => {
-                        // In top-down mode, move up; in side-scrolling mode, jump
-                        if let Some(level) = self.world.current_level() {
-                            match level.perspective {
-                                Perspective::SideScrolling => self.player.jump(),
-                                Perspective::TopDown => self.player.move_up(true),
-                            }
-                        }
-                    },
-                    (VirtualKeyCode::Up, ElementState::Released) => {
-                        self.player.move_up(false);
-                    },
-                    (VirtualKeyCode::Down, ElementState::Pressed) => {
-                        self.player.move_down(true);
-                    },
-                    (VirtualKeyCode::Down, ElementState::Released) => {
-                        self.player.move_down(false);
-                    },
-                    (VirtualKeyCode::Space, ElementState::Pressed) => {
-                        self.player.jump(); // Jump is also bound to space
-                    },
-                    (VirtualKeyCode::Tab, ElementState::Pressed) => {
-                        // Switch perspective/level on Tab
-                        if self.world.current_level == "test_level" {
-                            self.world.switch_level("topdown_level");
-                        } else {
-                            self.world.switch_level("test_level");
-                        }
-                        
-                        // Reset player position to the level's spawn point
-                        if let Some(level) = self.world.current_level() {
-                            self.player.x = level.spawn_point.0;
-                            self.player.y = level.spawn_point.1;
-                        }
-                    },
-                    _ => {}
-                }
-                // Returning false means we've handled the event
-                false
-            }
-            _ => false,
-        }
-    }
-    
-    fn update(&mut self, dt: f32) {
-        // Update player position and state
-        if let Some(level) = self.world.current_level() {
-            self.player.update(dt, level);
-        }
-        
-        // Update camera
-        self.update_camera(800.0, 600.0);  // Assuming screen size
-    }
-    
-    fn render(&mut self, device: &Device, queue: &Queue, surface: &Surface, 
-              config: &SurfaceConfiguration) -> Result<(), wgpu::SurfaceError> {
-        // Ensure assets are loaded
-        self.load_assets(device, queue);
-        
-        // Get a new frame
-        let frame = self.renderer.begin_frame(surface)?;
-        
-        // Clear the screen with a nice background color
-        let view = self.renderer.clear_screen(&frame, device, queue, wgpu::Color {
-            r: 0.4,
-            g: 0.6,
-            b: 0.9,
-            a: 1.0,
-        });
-        
-        // Render the level
-        if let Some(level) = self.world.current_level() {
-            for y in 0..level.height {
-                for x in 0..level.width {
-                    if let Some(tile) = level.get_tile(x, y) {
-                        match tile {
-                            TileType::Platform => {
-                                // Draw a platform tile
-                                self.renderer.draw_sprite(
-                                    device,
-                                    queue,
-                                    &view,
-                                    "platform",
-                                    (x as f32 * 32.0) - self.camera_x,
-                                    (y as f32 * 32.0) - self.camera_y,
-                                    32.0,
-                                    32.0
-                                );
-                            },
-                            TileType::Wall => {
-                                // Draw a wall tile
-                                self.renderer.draw_sprite(
-                                    device,
-                                    queue,
-                                    &view,
-                                    "platform",  // Using the same texture for now
-                                    (x as f32 * 32.0) - self.camera_x,
-                                    (y as f32 * 32.0) - self.camera_y,
-                                    32.0,
-                                    32.0
-                                );
-                            },
-                            TileType::Evidence => {
-                                // Check if this evidence has been collected
-                                let evidence_id = format!("evidence_{}_{}", x, y);
-                                if !self.player.evidence_collected.contains(&evidence_id) {
-                                    // Draw evidence only if not collected
-                                    self.renderer.draw_sprite(
-                                        device,
-                                        queue,
-                                        &view,
-                                        "evidence",
-                                        (x as f32 * 32.0) - self.camera_x,
-                                        (y as f32 * 32.0) - self.camera_y,
-                                        32.0,
-                                        32.0
-                                    );
-                                }
-                            },
-                            _ => {}
-                        }
-                    }
-                }
-            }
-        }
-        
-        // Render the player
-        let player_sprite = if self.player.facing_right { "player" } else { "player" }; // We'll add flipped sprites later
-        self.renderer.draw_sprite(
-            device,
-            queue,
-            &view,
-            player_sprite,
-            self.player.x - self.camera_x,
-            self.player.y - self.camera_y,
-            self.player.width,
-            self.player.height
-        );
-        
-        // Present the frame
-        self.renderer.end_frame(frame);
-        
-        Ok(())
-    }
+// src/game/states/playing.rs
+use crate::engine::state::{GameState, Transition};
+use crate::engine::graphics::{Renderer, SpriteOptions, SpriteRect, Space};
+use crate::engine::text::{TextAlign, TextSpan, TextStyle};
+use crate::engine::camera::Camera2D;
+use crate::engine::animation::AnimationClip;
+use crate::engine::atlas::AtlasBuilder;
+use crate::engine::watch::FileWatcher;
+use crate::ecs::components::{Collider, Inventory, Position, PreviousPosition, Respawn, Talker};
+use crate::ecs::resources::{CameraFollow, Events, GameEvent, Input, RenderList, Time};
+use crate::ecs::systems;
+use crate::game::entities::evidence::spawn_evidence;
+use crate::game::entities::npc::spawn_npc;
+use crate::game::entities::player::spawn_player;
+use crate::game::level::{Level, World, Perspective};
+use crate::game::tilemap::TilemapRenderer;
+use crate::game::screen::SharedScreenRenderer;
+use crate::game::states::pause::PauseState;
+use crate::game::states::level_complete::{LevelCompleteState, LevelSummary};
+use crate::levels::error::LevelError;
+use crate::levels::loader::{self, CampaignData, MovementConfig};
+use legion::{Entity, EntityStore, IntoQuery, Resources, Schedule};
+use nalgebra as na;
+use winit::event::{WindowEvent, VirtualKeyCode, ElementState, KeyboardInput};
+use wgpu::{Device, Queue, SurfaceConfiguration, TextureView};
+use std::path::Path;
+use std::fs;
+use std::ops::DerefMut;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Sky blue behind the level
+const BACKGROUND_COLOR: wgpu::Color = wgpu::Color {
+    r: 0.4,
+    g: 0.6,
+    b: 0.9,
+    a: 1.0,
+};
+
+// Perspective zones are shaded so players can see where the rules change
+const TOP_DOWN_ZONE_COLOR: [f32; 4] = [0.3, 1.0, 0.4, 0.15];
+const SIDE_SCROLLING_ZONE_COLOR: [f32; 4] = [1.0, 0.6, 0.2, 0.15];
+
+// Every PNG in here is packed into the sprite atlas, named after its file
+const SPRITE_DIR: &str = "src/assets";
+const ATLAS_PAGE_SIZE: u32 = 1024;
+
+// Font used for the HUD and dialogue
+pub const FONT_PATH: &str = "src/assets/fonts/DejaVuSansMono.ttf";
+const UI_FONT: &str = "ui";
+const UI_FONT_SIZE: f32 = 20.0;
+
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const HIGHLIGHT_COLOR: [f32; 4] = [1.0, 0.85, 0.3, 1.0];
+const DIALOGUE_BOX_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.75];
+
+// How long a dialogue message stays on screen, in seconds
+const DIALOGUE_DURATION: f32 = 3.0;
+
+// Where F12 screenshots are written
+const SCREENSHOT_DIR: &str = "screenshots";
+
+// Every JSON file in here is a level
+const LEVEL_DIR: &str = "assets/levels";
+
+// The order the levels are played in
+const CAMPAIGN_PATH: &str = "assets/campaign.json";
+
+// How the player moves in each perspective. Reloaded while the game runs
+// whenever the file changes, checking every MOVEMENT_CHECK_INTERVAL seconds.
+const MOVEMENT_PATH: &str = "assets/movement.json";
+const MOVEMENT_CHECK_INTERVAL: f32 = 0.5;
+
+// Exits are drawn as tinted boxes, green once they can be used
+const OPEN_EXIT_COLOR: [f32; 4] = [0.2, 1.0, 0.3, 0.4];
+const LOCKED_EXIT_COLOR: [f32; 4] = [1.0, 0.2, 0.2, 0.4];
+
+// A message shown in a box at the bottom of the screen for a while
+struct Dialogue {
+    spans: Vec<TextSpan>,
+    time_left: f32,
+}
+
+// The levels the game ships with
+pub fn create_world() -> Result<World, LevelError> {
+    World::load_dir(Path::new(LEVEL_DIR))
+}
+
+// The campaign the game ships with, checked against the levels in `world`
+pub fn create_campaign(world: &World) -> Result<CampaignData, LevelError> {
+    let campaign = loader::load_campaign(Path::new(CAMPAIGN_PATH))?;
+    world.check_campaign(&campaign)?;
+    Ok(campaign)
+}
+
+// How many of the level's evidence items the player has. The inventory can
+// also hold evidence from earlier levels, which doesn't count here.
+fn evidence_found(level: &Level, inventory: &Inventory) -> usize {
+    level.evidence.iter()
+        .filter(|evidence| inventory.evidence.contains(&evidence.id))
+        .count()
+}
+
+pub struct PlayingState {
+    // The player, evidence and NPCs live in here, and the schedule's systems
+    // move them about once per fixed step
+    ecs: legion::World,
+    resources: Resources,
+    schedule: Schedule,
+    player: Entity,
+    // Everything spawned for the current level, removed again on switching level
+    level_entities: Vec<Entity>,
+    renderer: Renderer,
+    tilemap: TilemapRenderer,
+    world: World,
+    campaign: CampaignData,
+    // Set while standing in an exit that's still locked, so the hint is only shown once
+    at_locked_exit: bool,
+    // The trigger and NPC the player is currently inside, so each only speaks once per visit
+    current_trigger: Option<usize>,
+    current_npc: Option<Entity>,
+    camera: Camera2D,
+    assets_loaded: bool,
+    screenshot_requested: bool,
+    dialogue: Option<Dialogue>,
+    movement_watcher: FileWatcher,
+    // Handed on to the pause and level complete screens
+    screen: SharedScreenRenderer,
+    // The player's evidence and score when they arrived in this level, for Restart Level
+    level_start: Inventory,
+}
+
+impl PlayingState {
+    // Fails if the levels, campaign or movement config can't be loaded
+    pub fn new(device: &Device,
+               queue: &Queue,
+               format: wgpu::TextureFormat,
+               screen: SharedScreenRenderer) -> Result<Self, LevelError> {
+        let renderer = Renderer::new(device, queue, format);
+        let world = create_world()?;
+        let campaign = create_campaign(&world)?;
+        let movement = loader::load_movement_config(Path::new(MOVEMENT_PATH))?;
+        
+        let mut ecs = legion::World::default();
+        let player = spawn_player(&mut ecs, 100.0, 300.0);
+        
+        let mut state = Self {
+            ecs,
+            resources: Self::create_resources(movement),
+            schedule: systems::build_schedule(),
+            player,
+            level_entities: Vec::new(),
+            renderer,
+            tilemap: TilemapRenderer::new(),
+            world,
+            campaign,
+            at_locked_exit: false,
+            current_trigger: None,
+            current_npc: None,
+            camera: Camera2D::new(800.0, 600.0),
+            assets_loaded: false,
+            screenshot_requested: false,
+            dialogue: None,
+            movement_watcher: FileWatcher::new(Path::new(MOVEMENT_PATH), MOVEMENT_CHECK_INTERVAL),
+            screen,
+            level_start: Inventory::default(),
+        };
+        
+        // Start at the beginning of the campaign, at the level's spawn point
+        let first_level = state.campaign.levels[0].clone();
+        state.switch_level(&first_level);
+        
+        Ok(state)
+    }
+    
+    // Everything the systems expect to find, apart from the levels, which
+    // are only lent to them for each step
+    fn create_resources(movement: MovementConfig) -> Resources {
+        let mut resources = Resources::default();
+        resources.insert(movement);
+        resources.insert(Time { delta: 0.0 });
+        resources.insert(Input::default());
+        resources.insert(Events::default());
+        resources.insert(CameraFollow::default());
+        resources.insert(RenderList::default());
+        resources
+    }
+    
+    // Pick up changes to the movement config file. A file that doesn't parse
+    // is reported and ignored, so the game carries on with the last good one
+    // while it's being fixed.
+    fn reload_movement(&mut self) {
+        match loader::load_movement_config(self.movement_watcher.path()) {
+            Ok(movement) => {
+                self.resources.insert(movement);
+                println!("Reloaded {}", self.movement_watcher.path().display());
+            },
+            Err(error) => eprintln!("Keeping the old movement config: {}", error),
+        }
+    }
+    
+    // The keys the input system hands to the player on the next step
+    fn input(&mut self) -> impl DerefMut<Target = Input> + '_ {
+        self.resources.get_mut::<Input>()
+            .expect("Input resource missing")
+    }
+    
+    // Where the player is, in world pixels
+    fn player_position(&self) -> (f32, f32) {
+        self.ecs.entry_ref(self.player).ok()
+            .and_then(|entry| entry.get_component::<Position>().ok().map(|position| (position.0.x, position.0.y)))
+            .unwrap_or((0.0, 0.0))
+    }
+    
+    // A copy of the player's evidence and score
+    fn inventory(&self) -> Inventory {
+        self.ecs.entry_ref(self.player).ok()
+            .and_then(|entry| entry.get_component::<Inventory>().ok().cloned())
+            .unwrap_or_default()
+    }
+    
+    // Start the player with this evidence and score. Restarting from the pause
+    // menu passes what they had when the level began; moving on from the level
+    // complete screen only passes the score. Call before switch_level, so any
+    // of the level's evidence in here starts out collected.
+    pub fn set_inventory(&mut self, inventory: Inventory) {
+        let Some(mut entry) = self.ecs.entry(self.player) else { return };
+        if let Ok(current) = entry.get_component_mut::<Inventory>() {
+            *current = inventory;
+        }
+    }
+    
+    // Move to another level and put the player at its spawn point.
+    // Returns false if there is no level with that name.
+    pub fn switch_level(&mut self, name: &str) -> bool {
+        if !self.world.switch_level(name) {
+            return false;
+        }
+        
+        // Swap the old level's evidence and NPCs for the new one's
+        for entity in self.level_entities.drain(..) {
+            self.ecs.remove(entity);
+        }
+        self.current_npc = None;
+        
+        self.level_start = self.inventory();
+        let collected = self.level_start.evidence.clone();
+        let Some(level) = self.world.current_level() else { return true };
+        for evidence in &level.evidence {
+            let entity = spawn_evidence(&mut self.ecs, evidence, collected.contains(&evidence.id));
+            self.level_entities.push(entity);
+        }
+        for npc in &level.npcs {
+            self.level_entities.push(spawn_npc(&mut self.ecs, npc));
+        }
+        
+        // Straight to the spawn point, without interpolating from wherever the player was before
+        let spawn = na::Vector2::new(level.spawn_point.0, level.spawn_point.1);
+        if let Some(mut entry) = self.ecs.entry(self.player) {
+            if let Ok(position) = entry.get_component_mut::<Position>() {
+                position.0 = spawn;
+            }
+            if let Ok(previous) = entry.get_component_mut::<PreviousPosition>() {
+                previous.0 = spawn;
+            }
+            // Checkpoints from the last level don't count here
+            if let Ok(respawn) = entry.get_component_mut::<Respawn>() {
+                respawn.0 = spawn;
+            }
+        }
+        true
+    }
+    
+    // Load game assets
+    pub fn load_assets(&mut self, device: &Device, queue: &Queue) {
+        if self.assets_loaded {
+            return;
+        }
+        
+        // Pack all the sprites into atlas pages so a frame rarely has to switch textures
+        let mut atlas = AtlasBuilder::new(ATLAS_PAGE_SIZE);
+        atlas.add_dir(Path::new(SPRITE_DIR))
+            .expect("Failed to load sprites");
+        let atlas = atlas.build()
+            .expect("Failed to pack sprite atlas");
+        self.renderer.load_atlas(device, queue, &atlas);
+        
+        // The player sheet is laid out in rows of 32x48 frames, four to a row
+        self.renderer.set_frames("player", 32, 48, None)
+            .expect("Failed to load player texture");
+        
+        // One row per clip: idle, run, jump, fall
+        let player_clips = [
+            ("idle", AnimationClip::new(0, 4, true)),
+            ("run", AnimationClip::new(4, 4, true)),
+            ("jump", AnimationClip::new(8, 2, false)),
+            ("fall", AnimationClip::new(12, 2, false)),
+        ];
+        for (name, clip) in player_clips {
+            self.renderer.add_clip("player", name, clip)
+                .expect("Failed to add player animation");
+        }
+        
+        let font_bytes = fs::read(FONT_PATH)
+            .expect("Failed to read UI font");
+        self.renderer.load_font(device, queue, UI_FONT, &font_bytes, UI_FONT_SIZE)
+            .expect("Failed to load UI font");
+        
+        self.assets_loaded = true;
+    }
+    
+    // Save an image to the screenshot folder with a timestamped name
+    fn save_screenshot(image: &image::RgbaImage) -> Result<String, String> {
+        fs::create_dir_all(SCREENSHOT_DIR)
+            .map_err(|e| format!("Failed to create {}: {}", SCREENSHOT_DIR, e))?;
+        
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+        let path = Path::new(SCREENSHOT_DIR).join(format!("screenshot_{}.png", timestamp));
+        
+        image.save(&path).map_err(|e| format!("Failed to save {}: {}", path.display(), e))?;
+        Ok(path.display().to_string())
+    }
+    
+    // Show a message in the dialogue box
+    fn show_dialogue(&mut self, spans: Vec<TextSpan>) {
+        self.dialogue = Some(Dialogue {
+            spans,
+            time_left: DIALOGUE_DURATION,
+        });
+    }
+    
+    // Draw the evidence counter and any dialogue on top of the world
+    fn render_hud(&mut self, screen_width: f32, screen_height: f32) {
+        self.renderer.set_space(Space::Screen);
+        
+        let inventory = self.inventory();
+        let (found, total) = self.world.current_level()
+            .map_or((0, 0), |level| (evidence_found(level, &inventory), level.evidence.len()));
+        let counter = [
+            TextSpan::new("Evidence: ", TEXT_COLOR),
+            TextSpan::new(format!("{}/{}", found, total), HIGHLIGHT_COLOR),
+            TextSpan::new("  Score: ", TEXT_COLOR),
+            TextSpan::new(inventory.score.to_string(), HIGHLIGHT_COLOR),
+        ];
+        self.renderer.push_text(UI_FONT, &counter, 16.0, 12.0, &TextStyle::default());
+        
+        if let Some(dialogue) = &self.dialogue {
+            let margin = 24.0;
+            let padding = 16.0;
+            let box_width = screen_width - margin * 2.0;
+            let style = TextStyle {
+                max_width: Some(box_width - padding * 2.0),
+                align: TextAlign::Center,
+            };
+            
+            // Size the box to however many lines the message wrapped to
+            let text_height = self.renderer.measure_text(UI_FONT, &dialogue.spans, &style)
+                .map_or(0.0, |layout| layout.height);
+            let box_height = text_height + padding * 2.0;
+            let box_y = screen_height - margin - box_height;
+            
+            self.renderer.push_rect(margin, box_y, box_width, box_height, DIALOGUE_BOX_COLOR);
+            self.renderer.push_text(UI_FONT, &dialogue.spans, margin + padding, box_y + padding, &style);
+        }
+    }
+    
+    // Show a trigger's message or an NPC's dialogue when the player walks into them
+    fn check_triggers(&mut self) {
+        let (x, y) = self.player_position();
+        let Some(level) = self.world.current_level() else { return };
+        
+        let trigger = level.triggers.iter().position(|trigger| trigger.contains(x, y));
+        let trigger_message = trigger
+            .filter(|&index| self.current_trigger != Some(index))
+            .and_then(|index| level.triggers[index].message.clone());
+        
+        // NPCs are entities, so look for one whose box the player is standing in
+        let npc = <(Entity, &Position, &Collider, &Talker)>::query()
+            .iter(&self.ecs)
+            .find(|(_, position, collider, _)| {
+                let (left, top, right, bottom) = collider.bounds(position);
+                x >= left && x < right && y >= top && y < bottom
+            })
+            .map(|(entity, _, _, talker)| (*entity, talker.clone()));
+        let npc_dialogue = npc.as_ref()
+            .filter(|(entity, _)| self.current_npc != Some(*entity))
+            .and_then(|(_, talker)| talker.dialogue.clone().map(|dialogue| (talker.name.clone(), dialogue)));
+        
+        self.current_trigger = trigger;
+        self.current_npc = npc.map(|(entity, _)| entity);
+        
+        if let Some(message) = trigger_message {
+            self.show_dialogue(vec![TextSpan::new(message, TEXT_COLOR)]);
+        }
+        if let Some((name, dialogue)) = npc_dialogue {
+            self.show_dialogue(vec![
+                TextSpan::new(format!("{}: ", name), HIGHLIGHT_COLOR),
+                TextSpan::new(dialogue, TEXT_COLOR),
+            ]);
+        }
+    }
+    
+    // Finish the level if the player is in an exit they have the evidence for
+    fn check_exits(&mut self) -> Transition {
+        let (x, y) = self.player_position();
+        let inventory = self.inventory();
+        let Some(level) = self.world.current_level() else { return Transition::None };
+        let Some(exit) = level.exits.iter().find(|exit| exit.contains(x, y)) else {
+            self.at_locked_exit = false;
+            return Transition::None;
+        };
+        
+        let missing: Vec<String> = exit.required_evidence.iter()
+            .filter(|id| !inventory.evidence.contains(id))
+            .cloned()
+            .collect();
+        
+        if missing.is_empty() {
+            let level_score = level.evidence.iter()
+                .filter(|evidence| inventory.evidence.contains(&evidence.id))
+                .map(|evidence| evidence.points)
+                .sum();
+            let summary = LevelSummary {
+                level: self.world.current_level.clone(),
+                evidence_found: evidence_found(level, &inventory),
+                evidence_total: level.evidence.len(),
+                level_score,
+                total_score: inventory.score,
+                next_level: self.campaign.next_level(&self.world.current_level).map(|name| name.to_string()),
+            };
+            let screen = self.screen.clone();
+            return Transition::replace(move |_, _, _| {
+                Box::new(LevelCompleteState::new(screen, summary))
+            });
+        }
+        
+        // Say what's missing when the player first walks into a locked exit
+        if !self.at_locked_exit {
+            self.at_locked_exit = true;
+            let names: Vec<String> = missing.iter().map(|id| id.replace('_', " ")).collect();
+            self.show_dialogue(vec![
+                TextSpan::new("The way out is blocked. You still need: ", TEXT_COLOR),
+                TextSpan::new(names.join(", "), HIGHLIGHT_COLOR),
+            ]);
+        }
+        Transition::None
+    }
+    
+    // Centre the camera between its last two fixed-step positions
+    fn place_camera(&mut self, alpha: f32) {
+        let (screen_width, screen_height) = self.camera.visible_size();
+        let Some(follow) = self.resources.get::<CameraFollow>().map(|follow| *follow) else { return };
+        let x = follow.prev_x + (follow.x - follow.prev_x) * alpha;
+        let y = follow.prev_y + (follow.y - follow.prev_y) * alpha;
+        
+        // The camera itself is positioned by the centre of the view
+        self.camera.position.x = x + screen_width / 2.0;
+        self.camera.position.y = y + screen_height / 2.0;
+    }
+}
+
+impl GameState for PlayingState {
+    fn handle_event(&mut self, event: &WindowEvent) -> Transition {
+        match event {
+            WindowEvent::KeyboardInput { 
+                input: KeyboardInput {
+                    state, 
+                    virtual_keycode: Some(keycode),
+                    ..
+                },
+                ..
+            } => {
+                match (keycode, state) {
+                    (VirtualKeyCode::Escape, ElementState::Pressed) => {
+                        // Pause on Escape. The menu gets the key-up events from
+                        // now on, so stop moving rather than run off a ledge on resume.
+                        self.input().release_all();
+                        let level = self.world.current_level.clone();
+                        let inventory = self.level_start.clone();
+                        let screen = self.screen.clone();
+                        return Transition::push(move |_, _, _| {
+                            Box::new(PauseState::new(screen, level, inventory))
+                        });
+                    },
+                    (VirtualKeyCode::Left, ElementState::Pressed) => {
+                        self.input().left = true;
+                    },
+                    (VirtualKeyCode::Right, ElementState::Pressed) => {
+                        self.input().right = true;
+                    },
+                    (VirtualKeyCode::Left, ElementState::Released) => {
+                        self.input().left = false;
+                    },
+                    (VirtualKeyCode::Right, ElementState::Released) => {
+                        self.input().right = false;
+                    },
+                    (VirtualKeyCode::Up, ElementState::Pressed) => {
+                        self.input().set_up(true);
+                    },
+                    (VirtualKeyCode::Up, ElementState::Released) => {
+                        self.input().set_up(false);
+                    },
+                    (VirtualKeyCode::Down, ElementState::Pressed) => {
+                        self.input().down = true;
+                    },
+                    (VirtualKeyCode::Down, ElementState::Released) => {
+                        self.input().down = false;
+                    },
+                    (VirtualKeyCode::Space, ElementState::Pressed) => {
+                        self.input().set_space(true);
+                    },
+                    (VirtualKeyCode::Space, ElementState::Released) => {
+                        self.input().set_space(false);
+                    },
+                    (VirtualKeyCode::F12, ElementState::Pressed) => {
+                        // Grab the next rendered frame
+                        self.screenshot_requested = true;
+                    },
+                    _ => {}
+                }
+                Transition::None
+            }
+            _ => Transition::None,
+        }
+    }
+    
+    fn fixed_update(&mut self, dt: f32) -> Transition {
+        self.resources.insert(Time { delta: dt });
+        let (view_width, view_height) = self.camera.visible_size();
+        if let Some(mut follow) = self.resources.get_mut::<CameraFollow>() {
+            follow.view_width = view_width;
+            follow.view_height = view_height;
+        }
+        
+        // Run the systems. They need the levels for collisions and perspective
+        // zones, so lend them to the schedule for the step.
+        let levels = std::mem::replace(&mut self.world, World::new());
+        self.resources.insert(levels);
+        self.schedule.execute(&mut self.ecs, &mut self.resources);
+        self.world = self.resources.remove::<World>()
+            .expect("Levels missing after running the systems");
+        
+        let events: Vec<GameEvent> = self.resources.get_mut::<Events>()
+            .map(|mut events| events.0.drain(..).collect())
+            .unwrap_or_default();
+        for event in events {
+            match event {
+                // Let the player know when they pick something up
+                GameEvent::EvidenceCollected { points, .. } => {
+                    let evidence_count = self.inventory().evidence.len();
+                    let total = self.world.current_level().map_or(0, |level| level.evidence.len());
+                    self.show_dialogue(vec![
+                        TextSpan::new("Evidence collected! ", TEXT_COLOR),
+                        TextSpan::new(format!("{} of {}", evidence_count, total), HIGHLIGHT_COLOR),
+                        TextSpan::new(" pieces found so far. ", TEXT_COLOR),
+                        TextSpan::new(format!("+{} points", points), HIGHLIGHT_COLOR),
+                    ]);
+                },
+                GameEvent::CheckpointReached => {
+                    self.show_dialogue(vec![TextSpan::new("Checkpoint reached", HIGHLIGHT_COLOR)]);
+                },
+            }
+        }
+        
+        self.check_triggers();
+        self.check_exits()
+    }
+    
+    fn update(&mut self, dt: f32) -> Transition {
+        // Count down the current message
+        if let Some(dialogue) = &mut self.dialogue {
+            dialogue.time_left -= dt;
+            if dialogue.time_left <= 0.0 {
+                self.dialogue = None;
+            }
+        }
+        
+        if self.movement_watcher.poll(dt) {
+            self.reload_movement();
+        }
+        
+        Transition::None
+    }
+    
+    fn resize(&mut self, device: &Device, config: &SurfaceConfiguration) {
+        self.renderer.set_format(device, config.format);
+        self.camera.set_viewport(config.width as f32, config.height as f32);
+    }
+    
+    fn render(&mut self, device: &Device, queue: &Queue, view: &TextureView, 
+              config: &SurfaceConfiguration, alpha: f32) {
+        // Ensure assets are loaded
+        self.load_assets(device, queue);
+        
+        // Keep the camera's viewport in sync with the window and upload it,
+        // drawn in between the last two simulation steps
+        self.camera.set_viewport(config.width as f32, config.height as f32);
+        self.place_camera(alpha);
+        self.renderer.set_camera(queue, &self.camera);
+        
+        // Queue everything for this frame into one sprite batch
+        self.renderer.begin_batch();
+        
+        // Perspective zones go underneath everything else
+        if let Some(level) = self.world.current_level() {
+            for zone in &level.perspective_zones {
+                let color = match zone.perspective {
+                    Perspective::TopDown => TOP_DOWN_ZONE_COLOR,
+                    Perspective::SideScrolling => SIDE_SCROLLING_ZONE_COLOR,
+                };
+                self.renderer.push_rect(zone.x, zone.y, zone.width, zone.height, color);
+            }
+        }
+        
+        // Solid tiles come from the tilemap's cached chunks, only those on screen
+        if let Some(level) = self.world.levels.get_mut(&self.world.current_level) {
+            self.tilemap.render(&mut self.renderer, device, &self.world.current_level, level, &self.camera);
+        }
+        
+        // Exits, coloured by whether the player can use them yet
+        let inventory = self.inventory();
+        if let Some(level) = self.world.current_level() {
+            for exit in &level.exits {
+                let open = exit.required_evidence.iter()
+                    .all(|id| inventory.evidence.contains(id));
+                let color = if open { OPEN_EXIT_COLOR } else { LOCKED_EXIT_COLOR };
+                self.renderer.push_rect(exit.x, exit.y, exit.width, exit.height, color);
+            }
+        }
+        
+        // NPCs, evidence and the player, as the systems left them after the last
+        // step. Each is drawn where it would be between the last two fixed steps.
+        if let Some(list) = self.resources.get::<RenderList>() {
+            for item in &list.0 {
+                let (x, y) = item.interpolated_position(alpha);
+                let options = SpriteOptions {
+                    flip_x: item.flip_x,
+                    tint: item.tint,
+                    ..SpriteOptions::default()
+                };
+                match (item.sprite, item.animation) {
+                    (Some(name), Some((clip, frame))) => {
+                        self.renderer.push_animation(name, clip, frame, SpriteRect::new(x, y, item.width, item.height), options);
+                    },
+                    (Some(name), None) => {
+                        self.renderer.push_sprite_with(name, x, y, item.width, item.height, options);
+                    },
+                    // No art yet, so a box of the tint colour. Rects go by their top-left corner.
+                    (None, _) => {
+                        self.renderer.push_rect(
+                            x - item.width / 2.0,
+                            y - item.height / 2.0,
+                            item.width,
+                            item.height,
+                            item.tint
+                        );
+                    },
+                }
+            }
+        }
+        
+        // HUD and dialogue go on top, in screen pixels
+        self.render_hud(config.width as f32, config.height as f32);
+        
+        // Clear the screen with a nice background color and draw the whole batch in one pass
+        self.renderer.flush(device, queue, view, Some(BACKGROUND_COLOR));
+        
+        // Render the same batch offscreen if a screenshot was asked for
+        if self.screenshot_requested {
+            self.screenshot_requested = false;
+            let result = self.renderer
+                .render_to_image(device, queue, config.width, config.height, Some(BACKGROUND_COLOR))
+                .and_then(|image| Self::save_screenshot(&image));
+            match result {
+                Ok(path) => println!("Screenshot saved to {}", path),
+                Err(e) => eprintln!("Screenshot failed: {}", e),
+            }
+        }
+    }
 }
\ No newline at end of file
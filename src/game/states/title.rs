@@ -0,0 +1,166 @@
+// src/game/states/title.rs
+use crate::engine::state::{GameState, Transition};
+use crate::engine::text::{TextAlign, TextSpan, TextStyle};
+use crate::game::menu::Menu;
+use crate::game::screen::{self, SharedScreenRenderer, BACKGROUND_COLOR, LOGO_FONT, MENU_FONT, MENU_FONT_SIZE};
+use crate::game::states::playing::{create_world, PlayingState};
+use crate::game::states::error::or_error_screen;
+use crate::levels::error::LevelError;
+use winit::event::{WindowEvent, VirtualKeyCode};
+use wgpu::{Device, Queue, SurfaceConfiguration, TextureView};
+
+const LOGO_COLOR: [f32; 4] = [1.0, 0.85, 0.3, 1.0];
+const TAGLINE_COLOR: [f32; 4] = [0.7, 0.75, 0.85, 1.0];
+const HINT_COLOR: [f32; 4] = [0.25, 0.25, 0.3, 1.0];
+
+#[derive(Clone)]
+enum TitleAction {
+    NewGame,
+    Continue,
+    LevelSelect,
+    Settings,
+    Quit,
+}
+
+// Which list the title screen is showing
+enum Page {
+    Main,
+    LevelSelect,
+}
+
+pub struct TitleState {
+    screen: SharedScreenRenderer,
+    menu: Menu<TitleAction>,
+    // Picks a level by name. "Back" has no name.
+    level_menu: Menu<Option<String>>,
+    page: Page,
+}
+
+impl TitleState {
+    // Fails if the levels for Level Select can't be loaded
+    pub fn new(screen: SharedScreenRenderer) -> Result<Self, LevelError> {
+        let mut menu = Menu::new();
+        menu.add("New Game", TitleAction::NewGame, true);
+        // There are no save files or settings yet, so these can't be chosen
+        menu.add("Continue", TitleAction::Continue, false);
+        menu.add("Level Select", TitleAction::LevelSelect, true);
+        menu.add("Settings", TitleAction::Settings, false);
+        menu.add("Quit", TitleAction::Quit, true);
+
+        let mut level_menu = Menu::new();
+        for name in create_world()?.level_names() {
+            level_menu.add(&name, Some(name.clone()), true);
+        }
+        level_menu.add("Back", None, true);
+
+        Ok(Self {
+            screen,
+            menu,
+            level_menu,
+            page: Page::Main,
+        })
+    }
+
+    // Start playing, optionally on a specific level
+    fn start_game(&self, level: Option<String>) -> Transition {
+        let screen = self.screen.clone();
+        Transition::replace(move |device, queue, config| {
+            let playing = PlayingState::new(device, queue, config.format, screen.clone()).map(|mut playing| {
+                if let Some(level) = level {
+                    playing.switch_level(&level);
+                }
+                playing
+            });
+            or_error_screen(&screen, playing)
+        })
+    }
+
+    fn handle_key(&mut self, key: VirtualKeyCode) -> Transition {
+        match self.page {
+            Page::Main => match self.menu.handle_key(key) {
+                Some(TitleAction::NewGame) => self.start_game(None),
+                Some(TitleAction::LevelSelect) => {
+                    self.page = Page::LevelSelect;
+                    Transition::None
+                },
+                Some(TitleAction::Quit) => Transition::Quit,
+                Some(TitleAction::Continue) | Some(TitleAction::Settings) | None => Transition::None,
+            },
+            Page::LevelSelect => {
+                if key == VirtualKeyCode::Escape {
+                    self.page = Page::Main;
+                    return Transition::None;
+                }
+
+                match self.level_menu.handle_key(key) {
+                    Some(Some(level)) => self.start_game(Some(level)),
+                    Some(None) => {
+                        self.page = Page::Main;
+                        Transition::None
+                    },
+                    None => Transition::None,
+                }
+            },
+        }
+    }
+}
+
+impl GameState for TitleState {
+    fn handle_event(&mut self, event: &WindowEvent) -> Transition {
+        match screen::pressed_key(event) {
+            Some(key) => self.handle_key(key),
+            None => Transition::None,
+        }
+    }
+
+    fn resize(&mut self, device: &Device, config: &SurfaceConfiguration) {
+        self.screen.borrow_mut().set_format(device, config.format);
+    }
+
+    fn render(&mut self, device: &Device, queue: &Queue, view: &TextureView,
+              config: &SurfaceConfiguration, _alpha: f32) {
+        self.screen.borrow_mut().draw(device, queue, view, config, Some(BACKGROUND_COLOR), |renderer, width, height| {
+            let centred = TextStyle {
+                max_width: Some(width),
+                align: TextAlign::Center,
+            };
+
+            // Logo and tagline in the top third
+            let logo_y = height * 0.15;
+            let (_, logo_height) = renderer.push_text(
+                LOGO_FONT,
+                &[TextSpan::new("SPEECH", LOGO_COLOR)],
+                0.0,
+                logo_y,
+                &centred
+            );
+            renderer.push_text(
+                MENU_FONT,
+                &[TextSpan::new("Every piece of evidence tells a story", TAGLINE_COLOR)],
+                0.0,
+                logo_y + logo_height,
+                &centred
+            );
+
+            let menu_y = height * 0.45;
+            let hint = match self.page {
+                Page::Main => {
+                    self.menu.render(renderer, MENU_FONT, 0.0, menu_y, width);
+                    "Arrow keys to move, Enter to choose"
+                },
+                Page::LevelSelect => {
+                    self.level_menu.render(renderer, MENU_FONT, 0.0, menu_y, width);
+                    "Enter to play, Escape to go back"
+                },
+            };
+
+            renderer.push_text(
+                MENU_FONT,
+                &[TextSpan::new(hint, HINT_COLOR)],
+                0.0,
+                height - MENU_FONT_SIZE * 2.0,
+                &centred
+            );
+        });
+    }
+}
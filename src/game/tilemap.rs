@@ -0,0 +1,167 @@
+use crate::engine::graphics::{Renderer, SpriteOptions, StaticBatchId};
+use crate::engine::camera::Camera2D;
+use crate::game::level::{Level, TileType, HAZARD_TOP, TILE_SIZE};
+use wgpu::Device;
+
+// Side length of a chunk, in tiles
+const CHUNK_SIZE: usize = 16;
+
+struct Chunk {
+    batch: Option<StaticBatchId>,
+    // Tiles changed since the batch was built (or it was never built)
+    dirty: bool,
+}
+
+// Draws a level's solid tiles from static buffers, one per CHUNK_SIZE x CHUNK_SIZE
+// chunk. A chunk is only rebuilt after set_tile changes one of its tiles, and
+// only queued when the camera can see it, so big maps cost little per frame.
+#[derive(Default)]
+pub struct TilemapRenderer {
+    // The level the chunks were built from
+    level_name: String,
+    chunks_x: usize,
+    chunks_y: usize,
+    chunks: Vec<Chunk>,
+}
+
+impl TilemapRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Free every chunk's buffer. The next render starts from scratch.
+    pub fn clear(&mut self, renderer: &mut Renderer) {
+        for chunk in self.chunks.drain(..) {
+            if let Some(id) = chunk.batch {
+                renderer.remove_static_batch(id);
+            }
+        }
+        self.level_name.clear();
+        self.chunks_x = 0;
+        self.chunks_y = 0;
+    }
+
+    // Rebuild changed chunks that are on screen and queue them into the renderer's batch
+    pub fn render(&mut self,
+                  renderer: &mut Renderer,
+                  device: &Device,
+                  level_name: &str,
+                  level: &mut Level,
+                  camera: &Camera2D) {
+        let changed = level.take_changed_tiles();
+        let chunks_x = level.width.div_ceil(CHUNK_SIZE);
+        let chunks_y = level.height.div_ceil(CHUNK_SIZE);
+
+        if level_name != self.level_name || chunks_x != self.chunks_x || chunks_y != self.chunks_y {
+            // A different level, so everything has to be built again
+            self.clear(renderer);
+            self.level_name = level_name.to_string();
+            self.chunks_x = chunks_x;
+            self.chunks_y = chunks_y;
+            self.chunks = (0..chunks_x * chunks_y)
+                .map(|_| Chunk { batch: None, dirty: true })
+                .collect();
+        } else {
+            for (x, y) in changed {
+                let index = (y / CHUNK_SIZE) * self.chunks_x + x / CHUNK_SIZE;
+                self.chunks[index].dirty = true;
+            }
+        }
+
+        // Only the chunks overlapping the camera's view
+        let (left, top, right, bottom) = camera.visible_rect();
+        let chunk_pixels = CHUNK_SIZE as f32 * TILE_SIZE;
+        let first_x = (left / chunk_pixels).floor().max(0.0) as usize;
+        let first_y = (top / chunk_pixels).floor().max(0.0) as usize;
+        let last_x = ((right / chunk_pixels).ceil().max(0.0) as usize).min(self.chunks_x);
+        let last_y = ((bottom / chunk_pixels).ceil().max(0.0) as usize).min(self.chunks_y);
+
+        for chunk_y in first_y..last_y {
+            for chunk_x in first_x..last_x {
+                let index = chunk_y * self.chunks_x + chunk_x;
+
+                // Offscreen chunks stay dirty until they scroll into view
+                if self.chunks[index].dirty {
+                    self.rebuild_chunk(renderer, device, level, chunk_x, chunk_y);
+                }
+
+                if let Some(id) = self.chunks[index].batch {
+                    renderer.push_static_batch(id);
+                }
+            }
+        }
+    }
+
+    fn rebuild_chunk(&mut self,
+                     renderer: &mut Renderer,
+                     device: &Device,
+                     level: &Level,
+                     chunk_x: usize,
+                     chunk_y: usize) {
+        let index = chunk_y * self.chunks_x + chunk_x;
+        if let Some(old) = self.chunks[index].batch.take() {
+            renderer.remove_static_batch(old);
+        }
+
+        let xs = chunk_x * CHUNK_SIZE..((chunk_x + 1) * CHUNK_SIZE).min(level.width);
+        let ys = chunk_y * CHUNK_SIZE..((chunk_y + 1) * CHUNK_SIZE).min(level.height);
+
+        // Positions are tile centres in world pixels
+        let id = renderer.create_static_batch(device, |renderer| {
+            for y in ys {
+                for x in xs.clone() {
+                    if let Some(look) = level.get_tile(x, y).and_then(|&tile| tile_look(tile)) {
+                        let height = TILE_SIZE * look.height;
+                        let top = y as f32 * TILE_SIZE + TILE_SIZE * look.top;
+                        renderer.push_sprite_with(
+                            look.sprite,
+                            x as f32 * TILE_SIZE + TILE_SIZE / 2.0,
+                            top + height / 2.0,
+                            TILE_SIZE,
+                            height,
+                            SpriteOptions {
+                                tint: look.tint,
+                                ..SpriteOptions::default()
+                            }
+                        );
+                    }
+                }
+            }
+        });
+
+        self.chunks[index] = Chunk {
+            batch: Some(id),
+            dirty: false,
+        };
+    }
+}
+
+// How a static tile is drawn
+struct TileLook {
+    sprite: &'static str,
+    tint: [f32; 4],
+    // The part of the tile covered, as fractions of a tile from the top
+    top: f32,
+    height: f32,
+}
+
+impl TileLook {
+    fn new(sprite: &'static str, tint: [f32; 4], top: f32, height: f32) -> Option<Self> {
+        Some(Self { sprite, tint, top, height })
+    }
+}
+
+// Evidence is drawn separately since it changes when collected. Everything
+// else uses the platform texture, tinted, until it has art of its own.
+fn tile_look(tile: TileType) -> Option<TileLook> {
+    match tile {
+        TileType::Platform | TileType::Wall => TileLook::new("platform", [1.0, 1.0, 1.0, 1.0], 0.0, 1.0),
+        // A thin ledge along the top of the tile
+        TileType::OneWay => TileLook::new("platform", [0.85, 0.7, 0.5, 1.0], 0.0, 0.25),
+        TileType::Ladder => TileLook::new("platform", [0.6, 0.4, 0.2, 0.6], 0.0, 1.0),
+        // Spikes along the bottom, exactly as far up as they hurt
+        TileType::Hazard => TileLook::new("platform", [1.0, 0.2, 0.2, 1.0], HAZARD_TOP, 1.0 - HAZARD_TOP),
+        TileType::Checkpoint => TileLook::new("platform", [1.0, 0.85, 0.3, 0.35], 0.0, 1.0),
+        TileType::Empty | TileType::Evidence => None,
+    }
+}
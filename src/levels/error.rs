@@ -0,0 +1,114 @@
+// src/levels/error.rs
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Everything that can go wrong loading a level. Tile positions are (column, row).
+#[derive(Debug)]
+pub enum LevelError {
+    // A file couldn't be read
+    Io { path: PathBuf, error: io::Error },
+    // A JSON or Tiled file isn't valid JSON, or doesn't match the format
+    Json { path: PathBuf, line: usize, column: usize, message: String },
+    // An ASCII map uses a character that isn't a tile
+    UnknownGlyph { glyph: char, x: usize, y: usize },
+    MissingSpawn,
+    // Something has a different number of tiles than the level is wide or big,
+    // e.g. a ragged row in an ASCII map or a short Tiled layer
+    SizeMismatch { what: String, expected: usize, found: usize },
+    // A position that can't be in the level, e.g. a negative one
+    OutOfBounds { what: String, x: f32, y: f32 },
+    // An ASCII map with nothing in it
+    Empty,
+    // Anything else wrong with what's in a level file
+    Invalid(String),
+    NoLevels { dir: PathBuf },
+    DuplicateName { name: String, path: PathBuf },
+    // One of the errors above, in a particular file
+    InFile { path: PathBuf, error: Box<LevelError> },
+}
+
+impl LevelError {
+    // Say which file a problem came from, if the error doesn't already
+    pub fn in_file(self, path: &Path) -> Self {
+        match self {
+            LevelError::Io { .. } | LevelError::Json { .. } | LevelError::InFile { .. } => self,
+            error => LevelError::InFile {
+                path: path.to_path_buf(),
+                error: Box::new(error),
+            },
+        }
+    }
+
+    pub fn json(path: &Path, error: serde_json::Error) -> Self {
+        let (line, column) = (error.line(), error.column());
+        // serde_json puts the position on the end of its message, but we keep it separately
+        let message = error.to_string();
+        let message = message
+            .strip_suffix(&format!(" at line {} column {}", line, column))
+            .unwrap_or(&message)
+            .to_string();
+        LevelError::Json {
+            path: path.to_path_buf(),
+            line,
+            column,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LevelError::Io { path, error } => write!(f, "Failed to read {}: {}", path.display(), error),
+            LevelError::Json { path, line, column, message } => {
+                write!(f, "{}:{}:{}: {}", path.display(), line, column, message)
+            },
+            LevelError::UnknownGlyph { glyph, x, y } => {
+                write!(f, "unknown tile '{}' at column {}, row {}", glyph, x, y)
+            },
+            LevelError::MissingSpawn => write!(f, "no spawn point"),
+            LevelError::SizeMismatch { what, expected, found } => {
+                write!(f, "{} has {} tiles, expected {}", what, found, expected)
+            },
+            LevelError::OutOfBounds { what, x, y } => write!(f, "{} at ({}, {}) is outside the level", what, x, y),
+            LevelError::Empty => write!(f, "level is empty"),
+            LevelError::Invalid(message) => write!(f, "{}", message),
+            LevelError::NoLevels { dir } => write!(f, "No levels found in {}", dir.display()),
+            LevelError::DuplicateName { name, path } => {
+                write!(f, "Duplicate level name {} in {}", name, path.display())
+            },
+            LevelError::InFile { path, error } => write!(f, "{}: {}", path.display(), error),
+        }
+    }
+}
+
+impl std::error::Error for LevelError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LevelError::Io { error, .. } => Some(error),
+            LevelError::InFile { error, .. } => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_errors_keep_the_position_out_of_the_message() {
+        let error = serde_json::from_str::<serde_json::Value>("{\n  \"name\": \"level\",\n  oops\n}")
+            .expect_err("Broken JSON parsed");
+        let error = LevelError::json(Path::new("levels/broken.json"), error);
+        match &error {
+            LevelError::Json { line, column, message, .. } => {
+                assert_eq!((*line, *column), (3, 3));
+                assert!(!message.contains("line"), "{}", message);
+            },
+            _ => panic!("Expected a JSON error, got {:?}", error),
+        }
+        assert_eq!(error.to_string(), "levels/broken.json:3:3: key must be a string");
+    }
+}
@@ -1,7 +1,9 @@
 // src/levels/loader.rs
+use crate::levels::error::LevelError;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::fs::File;
-use std::io::BufReader;
+use std::fs;
+use std::path::Path;
 
 #[derive(Deserialize, Serialize, Debug)]
 pub struct LevelData {
@@ -10,15 +12,243 @@ pub struct LevelData {
     pub platforms: Vec<Platform>,
     pub evidence: Vec<Evidence>,
     pub spawn_point: (f32, f32),
+    // A one-tile exit, locked until required_evidence has been collected
     pub exit_point: (f32, f32),
+    #[serde(default)]
+    pub required_evidence: Vec<String>,
+    // Any other exits, each with its own requirements
+    #[serde(default)]
+    pub exits: Vec<ExitZone>,
+    // Areas that play in a different perspective from the rest of the level
+    #[serde(default)]
+    pub perspective_zones: Vec<PerspectiveZone>,
+    // Platforms that can be jumped up through and dropped down through
+    #[serde(default)]
+    pub one_way_platforms: Vec<Platform>,
+    // Climbable areas, in world pixels like platforms
+    #[serde(default)]
+    pub ladders: Vec<Platform>,
+    // Spikes, pits and anything else that sends the player back to their last checkpoint
+    #[serde(default)]
+    pub hazards: Vec<Platform>,
+    // Points the player comes back to after a hazard, once they've touched them.
+    // The whole tile each one is in counts.
+    #[serde(default)]
+    pub checkpoints: Vec<(f32, f32)>,
+    // Movement tuning just for this level. Anything left out comes from the
+    // shared movement config.
+    #[serde(default)]
+    pub movement: LevelMovement,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+// How a level (or part of one) is played. Shared by the level data and the
+// running game.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
 pub enum Perspective {
     SideScrolling,
     TopDown,
 }
 
+// Whether (x, y) is inside the rectangle with its top-left corner at (left,
+// top). The left and top edges are inside, the right and bottom edges aren't,
+// so rectangles that share an edge never both contain a point on it.
+pub fn rect_contains(left: f32, top: f32, width: f32, height: f32, x: f32, y: f32) -> bool {
+    x >= left && x < left + width && y >= top && y < top + height
+}
+
+// A rectangle, in world pixels, inside which the player moves in `perspective`
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct PerspectiveZone {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub perspective: Perspective,
+}
+
+impl PerspectiveZone {
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        rect_contains(self.x, self.y, self.width, self.height, x, y)
+    }
+}
+
+// A rectangle, in world pixels, that finishes the level when the player reaches it
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ExitZone {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    // Evidence ids the player needs before this exit opens
+    #[serde(default)]
+    pub required_evidence: Vec<String>,
+}
+
+impl ExitZone {
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        rect_contains(self.x, self.y, self.width, self.height, x, y)
+    }
+}
+
+// The order levels are played in, by level name
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct CampaignData {
+    pub name: String,
+    pub levels: Vec<String>,
+}
+
+impl CampaignData {
+    // The level after `current`, or None at the end of the campaign
+    pub fn next_level(&self, current: &str) -> Option<&str> {
+        let index = self.levels.iter().position(|name| name == current)?;
+        self.levels.get(index + 1).map(|name| name.as_str())
+    }
+}
+
+// Movement tuning for each perspective, shared by every level and read from
+// assets/movement.json. Speeds are in pixels per second. Anything left out of
+// the file keeps its default.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct MovementConfig {
+    pub side_scrolling: SideScrollingParams,
+    pub top_down: TopDownParams,
+}
+
+// A level's own movement tuning. Each value given replaces that one value in
+// the shared config, so the rest still follows assets/movement.json.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct LevelMovement {
+    pub side_scrolling: SideScrollingOverrides,
+    pub top_down: TopDownOverrides,
+}
+
+impl LevelMovement {
+    pub fn side_scrolling(&self, config: &MovementConfig) -> SideScrollingParams {
+        self.side_scrolling.apply(&config.side_scrolling)
+    }
+
+    pub fn top_down(&self, config: &MovementConfig) -> TopDownParams {
+        self.top_down.apply(&config.top_down)
+    }
+}
+
+// SideScrollingParams where every value is optional
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct SideScrollingOverrides {
+    pub acceleration: Option<f32>,
+    pub max_velocity: Option<f32>,
+    pub friction: Option<f32>,
+    pub jump_velocity: Option<f32>,
+    pub gravity: Option<f32>,
+    pub climb_speed: Option<f32>,
+    pub coyote_time: Option<f32>,
+    pub jump_buffer: Option<f32>,
+    pub jump_cut: Option<f32>,
+    pub air_control: Option<f32>,
+    pub apex_gravity: Option<f32>,
+    pub apex_threshold: Option<f32>,
+}
+
+impl SideScrollingOverrides {
+    pub fn apply(&self, base: &SideScrollingParams) -> SideScrollingParams {
+        SideScrollingParams {
+            acceleration: self.acceleration.unwrap_or(base.acceleration),
+            max_velocity: self.max_velocity.unwrap_or(base.max_velocity),
+            friction: self.friction.unwrap_or(base.friction),
+            jump_velocity: self.jump_velocity.unwrap_or(base.jump_velocity),
+            gravity: self.gravity.unwrap_or(base.gravity),
+            climb_speed: self.climb_speed.unwrap_or(base.climb_speed),
+            coyote_time: self.coyote_time.unwrap_or(base.coyote_time),
+            jump_buffer: self.jump_buffer.unwrap_or(base.jump_buffer),
+            jump_cut: self.jump_cut.unwrap_or(base.jump_cut),
+            air_control: self.air_control.unwrap_or(base.air_control),
+            apex_gravity: self.apex_gravity.unwrap_or(base.apex_gravity),
+            apex_threshold: self.apex_threshold.unwrap_or(base.apex_threshold),
+        }
+    }
+}
+
+// TopDownParams where every value is optional
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct TopDownOverrides {
+    pub speed: Option<f32>,
+}
+
+impl TopDownOverrides {
+    pub fn apply(&self, base: &TopDownParams) -> TopDownParams {
+        TopDownParams {
+            speed: self.speed.unwrap_or(base.speed),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(default)]
+pub struct SideScrollingParams {
+    // How quickly the player accelerates
+    pub acceleration: f32,
+    // Maximum running speed
+    pub max_velocity: f32,
+    // How quickly the player slows down on the ground
+    pub friction: f32,
+    // Initial upward velocity when jumping
+    pub jump_velocity: f32,
+    // Downward acceleration
+    pub gravity: f32,
+    // Speed up and down ladders
+    pub climb_speed: f32,
+    // Seconds after walking off a ledge that a jump still works
+    pub coyote_time: f32,
+    // Seconds before landing that a jump press is remembered for
+    pub jump_buffer: f32,
+    // Upward velocity is multiplied by this when jump is let go early
+    pub jump_cut: f32,
+    // Acceleration in the air, as a fraction of acceleration on the ground
+    pub air_control: f32,
+    // Gravity near the top of a jump, as a fraction of gravity, for a bit of hang time
+    pub apex_gravity: f32,
+    // Vertical speed below which the player counts as being near the top
+    pub apex_threshold: f32,
+}
+
+impl Default for SideScrollingParams {
+    fn default() -> Self {
+        Self {
+            acceleration: 1000.0,
+            max_velocity: 500.0,
+            friction: 800.0,
+            jump_velocity: 500.0,
+            gravity: 1500.0,
+            climb_speed: 200.0,
+            coyote_time: 0.1,
+            jump_buffer: 0.1,
+            jump_cut: 0.5,
+            air_control: 0.65,
+            apex_gravity: 0.5,
+            apex_threshold: 60.0,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(default)]
+pub struct TopDownParams {
+    // Walking speed in any direction
+    pub speed: f32,
+}
+
+impl Default for TopDownParams {
+    fn default() -> Self {
+        Self {
+            speed: 500.0,
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct Platform {
     pub x: f32,
@@ -35,9 +265,43 @@ pub struct Evidence {
     pub points: u32,
 }
 
-pub fn load_level(level_id: usize) -> Result<LevelData, Box<dyn std::error::Error>> {
-    let file = File::open(format!("assets/levels/level_{}.json", level_id))?;
-    let reader = BufReader::new(file);
-    let level_data = serde_json::from_reader(reader)?;
-    Ok(level_data)
+pub fn load_level_file(path: &Path) -> Result<LevelData, LevelError> {
+    read_json(path)
+}
+
+pub fn load_campaign(path: &Path) -> Result<CampaignData, LevelError> {
+    read_json(path)
+}
+
+pub fn load_movement_config(path: &Path) -> Result<MovementConfig, LevelError> {
+    read_json(path)
+}
+
+// Read a whole file and parse it, keeping where any syntax error is
+pub fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T, LevelError> {
+    let text = fs::read_to_string(path)
+        .map_err(|error| LevelError::Io { path: path.to_path_buf(), error })?;
+    serde_json::from_str(&text)
+        .map_err(|error| LevelError::json(path, error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_overrides_only_replace_the_values_they_give() {
+        let mut config = MovementConfig::default();
+        config.side_scrolling.gravity = 2000.0;
+        config.top_down.speed = 300.0;
+
+        let movement: LevelMovement = serde_json::from_str(r#"{ "side_scrolling": { "jump_velocity": 700 } }"#)
+            .expect("Failed to parse movement overrides");
+
+        let side_scrolling = movement.side_scrolling(&config);
+        assert_eq!(side_scrolling.jump_velocity, 700.0);
+        assert_eq!(side_scrolling.gravity, 2000.0);
+        assert_eq!(side_scrolling.max_velocity, config.side_scrolling.max_velocity);
+        assert_eq!(movement.top_down(&config).speed, 300.0);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,4 @@
+pub mod loader;
+pub mod tiled;
+pub mod validate;
+pub mod error;
\ No newline at end of file
@@ -0,0 +1,412 @@
+// src/levels/tiled.rs
+// Imports maps made in the Tiled editor (https://www.mapeditor.org), saved as
+// JSON (.tmj) with CSV tile layer data and tilesets embedded in the map.
+//
+// Tile layers become TileType. A tile's type comes from its class (or type, in
+// maps saved before Tiled 1.9) in the tileset, then from the layer's "tile"
+// property, and is a platform otherwise. Class names are "platform", "wall",
+// "one_way", "ladder", "hazard", "checkpoint" and "empty".
+//
+// Objects are picked by class too:
+//   spawn       - where the player starts (a point)
+//   exit        - finishes the level; "required_evidence" is a comma-separated list of ids
+//   evidence    - "id" (string) and "points" (int) properties
+//   trigger     - an area with an optional "message" property
+//   npc         - "dialogue" property, named after the object
+//   perspective - an area with a "perspective" property
+//
+// Map properties: "name" (defaults to the file name) and "perspective"
+// ("SideScrolling", the default, or "TopDown").
+use crate::game::level::{name_from_path, Level, Npc, Perspective, TileType, Trigger, DEFAULT_EVIDENCE_POINTS, TILE_SIZE};
+use crate::levels::error::LevelError;
+use crate::levels::loader::{read_json, ExitZone, PerspectiveZone};
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::Path;
+
+// Tiled keeps flip and rotation flags in the top bits of a gid
+const GID_MASK: u32 = 0x1FFF_FFFF;
+
+#[derive(Deserialize, Debug)]
+struct TiledMap {
+    width: usize,
+    height: usize,
+    tilewidth: f32,
+    tileheight: f32,
+    #[serde(default)]
+    infinite: bool,
+    layers: Vec<TiledLayer>,
+    #[serde(default)]
+    tilesets: Vec<TiledTileset>,
+    #[serde(default)]
+    properties: Vec<TiledProperty>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum TiledLayer {
+    TileLayer {
+        name: String,
+        // A list of gids in CSV layers, and a string in base64 ones. Left as
+        // JSON until the encoding has been checked, so that gets reported
+        // instead of a type error.
+        #[serde(default)]
+        data: Value,
+        #[serde(default)]
+        encoding: Option<String>,
+        #[serde(default)]
+        properties: Vec<TiledProperty>,
+    },
+    ObjectGroup {
+        objects: Vec<TiledObject>,
+    },
+    // Layer folders, whose layers are imported as if they were at the top level
+    Group {
+        layers: Vec<TiledLayer>,
+    },
+    // Nothing to import from image layers
+    ImageLayer {},
+}
+
+#[derive(Deserialize, Debug)]
+struct TiledTileset {
+    firstgid: u32,
+    // Set when the tileset is saved in its own file
+    #[serde(default)]
+    source: Option<String>,
+    #[serde(default)]
+    tiles: Vec<TiledTile>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TiledTile {
+    id: u32,
+    #[serde(default, alias = "type")]
+    class: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct TiledObject {
+    #[serde(default)]
+    name: String,
+    #[serde(default, alias = "type")]
+    class: String,
+    x: f32,
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    // Tile objects are positioned by their bottom-left corner
+    #[serde(default)]
+    gid: Option<u32>,
+    #[serde(default)]
+    properties: Vec<TiledProperty>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TiledProperty {
+    name: String,
+    value: Value,
+}
+
+// Look up a string property
+fn string_property<'a>(properties: &'a [TiledProperty], name: &str) -> Option<&'a str> {
+    properties.iter()
+        .find(|property| property.name == name)
+        .and_then(|property| property.value.as_str())
+}
+
+// Look up a property of any type
+fn property<'a>(properties: &'a [TiledProperty], name: &str) -> Option<&'a Value> {
+    properties.iter()
+        .find(|property| property.name == name)
+        .map(|property| &property.value)
+}
+
+fn parse_perspective(value: &str) -> Result<Perspective, LevelError> {
+    match value {
+        "SideScrolling" => Ok(Perspective::SideScrolling),
+        "TopDown" => Ok(Perspective::TopDown),
+        _ => Err(LevelError::Invalid(format!("Unknown perspective {}", value))),
+    }
+}
+
+fn parse_tile_type(class: &str) -> Result<TileType, LevelError> {
+    match class {
+        "platform" => Ok(TileType::Platform),
+        "wall" => Ok(TileType::Wall),
+        "empty" => Ok(TileType::Empty),
+        "one_way" => Ok(TileType::OneWay),
+        "ladder" => Ok(TileType::Ladder),
+        "hazard" => Ok(TileType::Hazard),
+        "checkpoint" => Ok(TileType::Checkpoint),
+        _ => Err(LevelError::Invalid(format!("Unknown tile class {}", class))),
+    }
+}
+
+// Load a Tiled map into a level. Returns the level's name along with it.
+pub fn load_map(path: &Path) -> Result<(String, Level), LevelError> {
+    let map: TiledMap = read_json(path)?;
+
+    let name = match string_property(&map.properties, "name") {
+        Some(name) => name.to_string(),
+        None => name_from_path(path),
+    };
+
+    let level = build_level(&map)
+        .map_err(|e| e.in_file(path))?;
+    Ok((name, level))
+}
+
+fn build_level(map: &TiledMap) -> Result<Level, LevelError> {
+    if map.infinite {
+        return Err(LevelError::Invalid("Infinite maps aren't supported".to_string()));
+    }
+    if let Some(tileset) = map.tilesets.iter().find(|tileset| tileset.source.is_some()) {
+        return Err(LevelError::Invalid(format!("Tileset starting at gid {} must be embedded in the map", tileset.firstgid)));
+    }
+
+    let perspective = match string_property(&map.properties, "perspective") {
+        Some(value) => parse_perspective(value)?,
+        None => Perspective::SideScrolling,
+    };
+    let mut level = Level::new(map.width, map.height, perspective);
+
+    // Maps can use any tile size, but the game's tiles are always TILE_SIZE
+    let scale_x = TILE_SIZE / map.tilewidth;
+    let scale_y = TILE_SIZE / map.tileheight;
+
+    let mut spawn_found = false;
+    let mut layers: Vec<&TiledLayer> = map.layers.iter().rev().collect();
+    while let Some(layer) = layers.pop() {
+        match layer {
+            TiledLayer::TileLayer { name, data, encoding, properties } => {
+                if encoding.as_deref().is_some_and(|encoding| encoding != "csv") {
+                    return Err(LevelError::Invalid(format!("Layer {} must use CSV encoding", name)));
+                }
+                let data = match data {
+                    Value::Null => Vec::new(),
+                    data => Vec::<u32>::deserialize(data)
+                        .map_err(|_| LevelError::Invalid(format!("Layer {} has tile data that isn't a list of gids", name)))?,
+                };
+                if data.len() != map.width * map.height {
+                    return Err(LevelError::SizeMismatch {
+                        what: format!("layer {}", name),
+                        expected: map.width * map.height,
+                        found: data.len(),
+                    });
+                }
+
+                let layer_tile = match string_property(properties, "tile") {
+                    Some(class) => parse_tile_type(class)?,
+                    None => TileType::Platform,
+                };
+
+                for (index, &gid) in data.iter().enumerate() {
+                    let gid = gid & GID_MASK;
+                    if gid == 0 {
+                        continue;
+                    }
+                    let tile = match tile_class(map, gid) {
+                        Some(class) => parse_tile_type(class)?,
+                        None => layer_tile,
+                    };
+                    // Later layers draw over earlier ones, but only where they have a tile
+                    level.set_tile(index % map.width, index / map.width, tile);
+                }
+            },
+            TiledLayer::ObjectGroup { objects } => {
+                for object in objects {
+                    // Everything in world pixels, from the top-left corner
+                    let x = object.x * scale_x;
+                    let top = if object.gid.is_some() { object.y - object.height } else { object.y };
+                    let y = top * scale_y;
+                    let width = object.width * scale_x;
+                    let height = object.height * scale_y;
+
+                    match object.class.as_str() {
+                        "spawn" => {
+                            if spawn_found {
+                                return Err(LevelError::Invalid("More than one spawn object".to_string()));
+                            }
+                            spawn_found = true;
+                            // The spawn point is where the middle of the player
+                            // goes. A point is that already; a rectangle or a
+                            // tile object has it in the middle.
+                            level.set_spawn_point(x + width / 2.0, y + height / 2.0);
+                        },
+                        "exit" => {
+                            let required_evidence = string_property(&object.properties, "required_evidence")
+                                .map(|ids| ids.split(',')
+                                    .map(|id| id.trim().to_string())
+                                    .filter(|id| !id.is_empty())
+                                    .collect())
+                                .unwrap_or_default();
+                            // A point is a one-tile exit at the tile it's in
+                            let (x, y, width, height) = if width > 0.0 && height > 0.0 {
+                                (x, y, width, height)
+                            } else {
+                                ((x / TILE_SIZE).floor() * TILE_SIZE, (y / TILE_SIZE).floor() * TILE_SIZE, TILE_SIZE, TILE_SIZE)
+                            };
+                            level.exits.push(ExitZone { x, y, width, height, required_evidence });
+                        },
+                        "evidence" => {
+                            let id = string_property(&object.properties, "id")
+                                .ok_or_else(|| LevelError::Invalid(format!("Evidence object {} has no id property", object.name)))?;
+                            let points = match property(&object.properties, "points") {
+                                Some(value) => value.as_u64()
+                                    .and_then(|points| u32::try_from(points).ok())
+                                    .ok_or_else(|| LevelError::Invalid(format!(
+                                        "Evidence {} has points {}, which isn't a whole number from 0 to {}",
+                                        id, value, u32::MAX
+                                    )))?,
+                                None => DEFAULT_EVIDENCE_POINTS,
+                            };
+                            // The tile under the middle of the object
+                            let tile_x = ((x + width / 2.0) / TILE_SIZE).floor().max(0.0) as usize;
+                            let tile_y = ((y + height / 2.0) / TILE_SIZE).floor().max(0.0) as usize;
+                            if x < 0.0 || y < 0.0 || tile_x >= level.width || tile_y >= level.height {
+                                return Err(LevelError::OutOfBounds { what: format!("evidence {}", id), x, y });
+                            }
+                            level.add_evidence(tile_x, tile_y, id, points);
+                        },
+                        "trigger" => {
+                            level.triggers.push(Trigger {
+                                name: object.name.clone(),
+                                x,
+                                y,
+                                width,
+                                height,
+                                message: string_property(&object.properties, "message").map(|s| s.to_string()),
+                            });
+                        },
+                        "npc" => {
+                            // Points get a one-tile body
+                            level.npcs.push(Npc {
+                                name: object.name.clone(),
+                                x,
+                                y,
+                                width: if width > 0.0 { width } else { TILE_SIZE },
+                                height: if height > 0.0 { height } else { TILE_SIZE },
+                                dialogue: string_property(&object.properties, "dialogue").map(|s| s.to_string()),
+                            });
+                        },
+                        "perspective" => {
+                            let value = string_property(&object.properties, "perspective")
+                                .ok_or_else(|| LevelError::Invalid(format!("Perspective object {} has no perspective property", object.name)))?;
+                            level.perspective_zones.push(PerspectiveZone {
+                                x,
+                                y,
+                                width,
+                                height,
+                                perspective: parse_perspective(value)?,
+                            });
+                        },
+                        // Anything else is for the designers, e.g. notes
+                        _ => {},
+                    }
+                }
+            },
+            TiledLayer::Group { layers: children } => {
+                // Keep the editor's order: the group's layers come before the ones after it
+                layers.extend(children.iter().rev());
+            },
+            TiledLayer::ImageLayer {} => {},
+        }
+    }
+
+    if !spawn_found {
+        return Err(LevelError::MissingSpawn);
+    }
+    Ok(level)
+}
+
+// The class the tileset gives a tile, if it gives it one
+fn tile_class(map: &TiledMap, gid: u32) -> Option<&str> {
+    // A tile belongs to the tileset with the highest firstgid at or below it
+    let tileset = map.tilesets.iter()
+        .filter(|tileset| tileset.firstgid <= gid)
+        .max_by_key(|tileset| tileset.firstgid)?;
+    let id = gid - tileset.firstgid;
+    tileset.tiles.iter()
+        .find(|tile| tile.id == id && !tile.class.is_empty())
+        .map(|tile| tile.class.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A 2x2 map with a floor, a spawn and `layer` and `objects` added
+    fn map(layer: &str, objects: &str) -> TiledMap {
+        let json = format!(r#"{{
+            "width": 2, "height": 2, "tilewidth": 32, "tileheight": 32,
+            "layers": [
+                {{ "type": "tilelayer", "name": "ground", {} }},
+                {{ "type": "objectgroup", "objects": [
+                    {{ "class": "spawn", "x": 16, "y": 16 }}
+                    {}
+                ] }}
+            ]
+        }}"#, layer, objects);
+        serde_json::from_str(&json).expect("Failed to parse test map")
+    }
+
+    fn error_message(map: &TiledMap) -> String {
+        build_level(map).err().expect("Map loaded").to_string()
+    }
+
+    #[test]
+    fn imports_csv_tile_layers() {
+        let level = build_level(&map(r#""data": [0, 0, 1, 1]"#, "")).expect("Failed to build level");
+        assert_eq!(level.get_tile(0, 0), Some(&TileType::Empty));
+        assert_eq!(level.get_tile(1, 1), Some(&TileType::Platform));
+    }
+
+    #[test]
+    fn spawn_is_a_point_or_the_middle_of_a_rectangle() {
+        let level = build_level(&map(r#""data": [0, 0, 1, 1]"#, "")).expect("Failed to build level");
+        assert_eq!(level.spawn_point, (16.0, 16.0));
+
+        let json = r#"{
+            "width": 2, "height": 2, "tilewidth": 32, "tileheight": 32,
+            "layers": [
+                { "type": "tilelayer", "name": "ground", "data": [0, 0, 1, 1] },
+                { "type": "objectgroup", "objects": [
+                    { "class": "spawn", "x": 32, "y": 0, "width": 32, "height": 32 }
+                ] }
+            ]
+        }"#;
+        let map: TiledMap = serde_json::from_str(json).expect("Failed to parse test map");
+        let level = build_level(&map).expect("Failed to build level");
+        assert_eq!(level.spawn_point, (48.0, 16.0));
+    }
+
+    #[test]
+    fn asks_for_csv_when_a_layer_is_base64() {
+        let map = map(r#""encoding": "base64", "compression": "zlib", "data": "eJxjYGBgAAAABAAB""#, "");
+        assert_eq!(error_message(&map), "Layer ground must use CSV encoding");
+    }
+
+    #[test]
+    fn evidence_points_default_when_left_out() {
+        let level = build_level(&map(
+            r#""data": [0, 0, 1, 1]"#,
+            r#", { "class": "evidence", "x": 32, "y": 0, "properties": [{ "name": "id", "type": "string", "value": "note" }] }"#,
+        )).expect("Failed to build level");
+        assert_eq!(level.evidence[0].points, DEFAULT_EVIDENCE_POINTS);
+    }
+
+    #[test]
+    fn evidence_points_too_big_for_a_u32_are_an_error() {
+        let map = map(
+            r#""data": [0, 0, 1, 1]"#,
+            r#", { "class": "evidence", "x": 32, "y": 0, "properties": [
+                { "name": "id", "type": "string", "value": "note" },
+                { "name": "points", "type": "int", "value": 5000000000 }
+            ] }"#,
+        );
+        assert!(error_message(&map).contains("Evidence note has points 5000000000"));
+    }
+}
@@ -0,0 +1,271 @@
+// src/levels/validate.rs
+// Checks for mistakes in levels that would otherwise only show up while
+// playing them. Used by the speech-lint tool, which wants every problem in a
+// level rather than just the first one that stops it loading.
+use crate::game::entities::player::{PLAYER_HEIGHT, PLAYER_WIDTH};
+use crate::game::level::{ascii_rows, Level, TileType, TILE_SIZE};
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+
+// Something wrong with a level. Tile positions are (column, row).
+#[derive(Clone, Debug, PartialEq)]
+pub enum Problem {
+    // An ASCII map with nothing in it
+    Empty,
+    // An ASCII row that isn't as long as the first one
+    RaggedRow { row: usize, expected: usize, found: usize },
+    MissingSpawn,
+    DuplicateSpawn { tiles: Vec<(usize, usize)> },
+    // The player would start stuck inside a solid tile
+    SpawnInGeometry { tile: (usize, usize) },
+    // The player would be sent straight back to the spawn point, forever
+    SpawnInHazard { tile: (usize, usize) },
+    // No open path from the spawn point leads to this exit
+    UnreachableExit { tile: (usize, usize) },
+    EvidenceInSolid { id: String, tile: (usize, usize) },
+    DuplicateEvidenceId { id: String },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::Empty => write!(f, "level is empty"),
+            Problem::RaggedRow { row, expected, found } => {
+                write!(f, "row {} is {} tiles wide, expected {}", row, found, expected)
+            },
+            Problem::MissingSpawn => write!(f, "no spawn point"),
+            Problem::DuplicateSpawn { tiles } => {
+                let tiles: Vec<String> = tiles.iter().map(|(x, y)| format!("({}, {})", x, y)).collect();
+                write!(f, "more than one spawn point, at {}", tiles.join(", "))
+            },
+            Problem::SpawnInGeometry { tile: (x, y) } => {
+                write!(f, "player spawns overlapping the solid tile at ({}, {})", x, y)
+            },
+            Problem::SpawnInHazard { tile: (x, y) } => {
+                write!(f, "player spawns touching the hazard at ({}, {})", x, y)
+            },
+            Problem::UnreachableExit { tile: (x, y) } => {
+                write!(f, "exit at ({}, {}) can't be reached from the spawn point", x, y)
+            },
+            Problem::EvidenceInSolid { id, tile: (x, y) } => {
+                write!(f, "evidence {} is inside the solid tile at ({}, {})", id, x, y)
+            },
+            Problem::DuplicateEvidenceId { id } => write!(f, "more than one piece of evidence has the id {}", id),
+        }
+    }
+}
+
+// Check the text of an ASCII map, before Level::from_string sees it
+pub fn check_ascii(text: &str) -> Vec<Problem> {
+    // Read the rows the same way from_string does
+    let lines = ascii_rows(text);
+    if lines.is_empty() {
+        return vec![Problem::Empty];
+    }
+
+    let mut problems = Vec::new();
+    let expected = lines[0].chars().count();
+    let mut spawns = Vec::new();
+    for (y, line) in lines.iter().enumerate() {
+        let found = line.chars().count();
+        if found != expected {
+            problems.push(Problem::RaggedRow { row: y, expected, found });
+        }
+        for (x, c) in line.chars().enumerate() {
+            if c == 'S' {
+                spawns.push((x, y));
+            }
+        }
+    }
+
+    match spawns.len() {
+        0 => problems.push(Problem::MissingSpawn),
+        1 => {},
+        _ => problems.push(Problem::DuplicateSpawn { tiles: spawns }),
+    }
+    problems
+}
+
+// Check a loaded level, whatever format it came from
+pub fn check_level(level: &Level) -> Vec<Problem> {
+    let mut problems = Vec::new();
+
+    // The player's box at the spawn point, which is its centre
+    let (spawn_x, spawn_y) = level.spawn_point;
+    let spawn_tiles = tiles_under(
+        spawn_x - PLAYER_WIDTH / 2.0,
+        spawn_y - PLAYER_HEIGHT / 2.0,
+        PLAYER_WIDTH,
+        PLAYER_HEIGHT
+    );
+    let mut spawn_blocked = false;
+    for &(x, y) in &spawn_tiles {
+        if level.is_solid(x, y) {
+            problems.push(Problem::SpawnInGeometry { tile: (x, y) });
+            spawn_blocked = true;
+        }
+        if level.get_tile(x, y) == Some(&TileType::Hazard) {
+            problems.push(Problem::SpawnInHazard { tile: (x, y) });
+        }
+    }
+
+    // Only worth looking for paths from a spawn point the player can move from
+    if !spawn_blocked {
+        let reachable = reachable_tiles(level, &spawn_tiles);
+        for exit in &level.exits {
+            let tiles = tiles_under(exit.x, exit.y, exit.width, exit.height);
+            if !tiles.iter().any(|tile| reachable.contains(tile)) {
+                let tile = tiles.first().copied().unwrap_or((0, 0));
+                problems.push(Problem::UnreachableExit { tile });
+            }
+        }
+    }
+
+    let mut ids = HashSet::new();
+    let mut duplicates = HashSet::new();
+    for evidence in &level.evidence {
+        if level.is_solid(evidence.x, evidence.y) {
+            problems.push(Problem::EvidenceInSolid {
+                id: evidence.id.clone(),
+                tile: (evidence.x, evidence.y),
+            });
+        }
+        // Report each duplicated id once
+        if !ids.insert(evidence.id.as_str()) && duplicates.insert(evidence.id.as_str()) {
+            problems.push(Problem::DuplicateEvidenceId { id: evidence.id.clone() });
+        }
+    }
+
+    problems
+}
+
+// Every tile a rectangle in world pixels overlaps. Tiles left of or above the
+// level are left out.
+fn tiles_under(x: f32, y: f32, width: f32, height: f32) -> Vec<(usize, usize)> {
+    let first_x = (x / TILE_SIZE).floor().max(0.0) as usize;
+    let first_y = (y / TILE_SIZE).floor().max(0.0) as usize;
+    // A box ending exactly on a tile edge doesn't touch the next tile
+    let last_x = ((x + width) / TILE_SIZE).ceil().max(0.0) as usize;
+    let last_y = ((y + height) / TILE_SIZE).ceil().max(0.0) as usize;
+
+    let mut tiles = Vec::new();
+    for ty in first_y..last_y.max(first_y + 1) {
+        for tx in first_x..last_x.max(first_x + 1) {
+            tiles.push((tx, ty));
+        }
+    }
+    tiles
+}
+
+// Flood fill through open tiles from the start tiles. This ignores jump height,
+// so in side-scrolling levels it only finds exits that are sealed off, not
+// ones that are too high to jump to.
+fn reachable_tiles(level: &Level, start: &[(usize, usize)]) -> HashSet<(usize, usize)> {
+    let mut reached: HashSet<(usize, usize)> = start.iter().copied().collect();
+    let mut queue: VecDeque<(usize, usize)> = start.iter().copied().collect();
+
+    while let Some((x, y)) = queue.pop_front() {
+        let mut neighbours = vec![(x + 1, y), (x, y + 1)];
+        if x > 0 {
+            neighbours.push((x - 1, y));
+        }
+        if y > 0 {
+            neighbours.push((x, y - 1));
+        }
+
+        for next in neighbours {
+            if !level.is_solid(next.0, next.1) && reached.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    reached
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::level::Perspective;
+
+    fn level(rows: &[&str]) -> Level {
+        Level::from_string(&rows.join("\n"), Perspective::SideScrolling)
+            .expect("Failed to load test map")
+    }
+
+    #[test]
+    fn a_tidy_level_has_no_problems() {
+        let rows = [
+            "#####",
+            "#..E#",
+            "#S.X#",
+            "#####",
+        ];
+        assert_eq!(check_ascii(&rows.join("\n")), Vec::new());
+        assert_eq!(check_level(&level(&rows)), Vec::new());
+    }
+
+    #[test]
+    fn finds_ragged_rows() {
+        let problems = check_ascii("S..\n##\n###");
+        assert_eq!(problems, vec![Problem::RaggedRow { row: 1, expected: 3, found: 2 }]);
+    }
+
+    #[test]
+    fn finds_every_spawn_point_when_there_are_several() {
+        let problems = check_ascii("S.S\n###");
+        assert_eq!(problems, vec![Problem::DuplicateSpawn { tiles: vec![(0, 0), (2, 0)] }]);
+    }
+
+    #[test]
+    fn finds_evidence_inside_solid_tiles() {
+        let mut level = level(&[
+            "#####",
+            "#...#",
+            "#S.X#",
+            "#####",
+        ]);
+        level.add_evidence(2, 3, "buried_watch", 100);
+        assert_eq!(check_level(&level), vec![Problem::EvidenceInSolid {
+            id: "buried_watch".to_string(),
+            tile: (2, 3),
+        }]);
+    }
+
+    #[test]
+    fn reports_each_duplicate_evidence_id_once() {
+        let mut level = level(&[
+            "#####",
+            "#...#",
+            "#S.X#",
+            "#####",
+        ]);
+        for x in 1..4 {
+            level.add_evidence(x, 1, "letter", 100);
+        }
+        assert_eq!(check_level(&level), vec![Problem::DuplicateEvidenceId { id: "letter".to_string() }]);
+    }
+
+    #[test]
+    fn finds_exits_sealed_off_from_the_spawn() {
+        let level = level(&[
+            "#######",
+            "#...#.#",
+            "#S..#X#",
+            "#######",
+        ]);
+        assert_eq!(check_level(&level), vec![Problem::UnreachableExit { tile: (5, 2) }]);
+    }
+
+    #[test]
+    fn flood_fill_stops_at_solid_tiles() {
+        let level = level(&[
+            "#####",
+            "#S#.#",
+            "#####",
+        ]);
+        let reached = reachable_tiles(&level, &[(1, 1)]);
+        assert!(reached.contains(&(1, 1)));
+        assert!(!reached.contains(&(3, 1)));
+        assert!(!reached.iter().any(|&(x, y)| level.is_solid(x, y)));
+    }
+}
@@ -0,0 +1,6 @@
+// The game's modules as a library, so tools in src/bin can load levels
+// exactly the way the game does
+pub mod ecs;
+pub mod engine;
+pub mod game;
+pub mod levels;
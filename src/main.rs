@@ -1,14 +1,13 @@
-mod engine;
-mod game;
-
 use winit::{
-    event::{Event, WindowEvent},
+    event::Event,
     event_loop::{ControlFlow, EventLoop},
-    window::{Window, WindowBuilder},
+    window::WindowBuilder,
 };
-use engine::state::StateManager;
-use engine::graphics::Timer;
-use game::states::playing::PlayingState;
+use speech_game::engine::state::StateManager;
+use speech_game::engine::graphics::Timer;
+use speech_game::game::screen::ScreenRenderer;
+use speech_game::game::states::title::TitleState;
+use speech_game::game::states::error::or_error_screen;
 
 fn main() {
     // Initialize the event loop
@@ -21,40 +20,14 @@ fn main() {
         .build(&event_loop)
         .expect("Failed to create window");
     
-    // This is a bit complex because of Rust's ownership model
-    let mut state_manager = {
-        // Create initial resources
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(),
-            dx12_shader_compiler: Default::default(),
-        });
-        
-        let surface = unsafe { instance.create_surface(&window) }
-            .expect("Failed to create surface");
-        
-        let adapter = futures::executor::block_on(instance.request_adapter(
-            &wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            },
-        )).expect("Failed to find an appropriate adapter");
-        
-        let (device, queue) = futures::executor::block_on(adapter.request_device(
-            &wgpu::DeviceDescriptor {
-                features: wgpu::Features::empty(),
-                limits: wgpu::Limits::default(),
-                label: None,
-            },
-            None,
-        )).expect("Failed to create device");
-        
-        // Create our proper playing state with the device
-        let playing_state = Box::new(PlayingState::new(&device, &queue));
-        
-        // Create the state manager
-        StateManager::new(window, device, queue, playing_state)
-    };
+    // Create the state manager. It sets up the GPU for this window and builds our
+    // title screen, and the renderer all the menu screens share, once it knows
+    // which format the surface uses. If the levels are broken we start on a
+    // screen saying so instead.
+    let mut state_manager = StateManager::new(window, |device, queue, config| {
+        let screen = ScreenRenderer::new(device, queue, config.format);
+        or_error_screen(&screen, TitleState::new(screen.clone()))
+    });
     
     // Create a timer for calculating delta time
     let mut timer = Timer::new();
@@ -69,7 +42,8 @@ fn main() {
                 window_id 
             } if window_id == state_manager.window.id() => {
                 // Check if our state manager wants to exit
-                if state_manager.handle_window_event(&event) {
+                let close_requested = state_manager.handle_window_event(&event);
+                if close_requested {
                     println!("Window close requested!");
                     *control_flow = ControlFlow::Exit;
                 }
@@ -78,8 +52,11 @@ fn main() {
                 // Calculate delta time
                 let dt = timer.delta();
                 
-                // Update game state
-                state_manager.update(dt);
+                // Update game state, which may ask to quit
+                if state_manager.update(dt) {
+                    *control_flow = ControlFlow::Exit;
+                    return;
+                }
                 
                 // Request to redraw the window
                 state_manager.window.request_redraw();